@@ -0,0 +1,218 @@
+/*!
+Post-game review: scores every position of a saved game and marks the moves that threw
+points away.
+
+The game is replayed the way `read_state_file` loads it, so a move the rules reject is
+skipped and the rest still apply. Each position before a move has all its legal moves
+ranked by [`rank_moves`], which gives the played move and the engine's choice scores from
+the same search. The difference is what the move lost.
+*/
+
+use std::fmt;
+
+use rayon::prelude::*;
+
+use crate::{
+    game::Instance,
+    movegen::Move,
+    piece::Side,
+    record::GameRecord,
+    search::{format_pv, is_win_score, outcome_score, rank_moves, Limits, RankedMove, WIN},
+};
+
+/// Search depth used when none is given
+pub const DEFAULT_DEPTH: u32 = 3;
+/// Points a move has to lose to be marked a mistake
+pub const MISTAKE: i32 = 50;
+/// Points a move has to lose to be marked a blunder, about a passive
+pub const BLUNDER: i32 = 150;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mark {
+    /// a forced win was on the board and the move let it go
+    MissedWin,
+    Blunder,
+    Mistake,
+}
+
+impl Mark {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Mark::MissedWin => "??",
+            Mark::Blunder => "??",
+            Mark::Mistake => "?",
+        }
+    }
+}
+
+impl fmt::Display for Mark {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Mark::MissedWin => "missed win",
+            Mark::Blunder => "blunder",
+            Mark::Mistake => "mistake",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Annotation {
+    /// turn number starting from 1
+    pub ply: usize,
+    pub side: Side,
+    pub mv: Move,
+    /// false if the rules rejected the move and it was skipped
+    pub legal: bool,
+    /// score of the position before the move, from White's point of view
+    pub before: i32,
+    /// score of the position after the move, from White's point of view
+    pub after: i32,
+    /// score the mover gave up against the engine's choice
+    pub loss: i32,
+    pub mark: Option<Mark>,
+    /// the move was a winning one made without calling first
+    pub missed_call: bool,
+    /// engine's choice, given for marked moves and missed calls
+    pub best: Option<RankedMove>,
+}
+
+/// Returns score for side to move of ctx as seen from White
+fn white_score(side: Side, score: i32) -> i32 {
+    match side {
+        Side::White => score,
+        Side::Orange => -score,
+    }
+}
+
+/// Formats a White point of view score, forced results as turns to the end
+pub fn format_score(score: i32) -> String {
+    if is_win_score(score) {
+        let side = if score > 0 { Side::White } else { Side::Orange };
+        match WIN - score.abs() {
+            0 => format!("{} won", side),
+            turns => format!("{} wins in {}", side, turns),
+        }
+    } else {
+        format!("{:+}", score)
+    }
+}
+
+/// Scores the position of ctx for its side to move
+fn position_score(ctx: &Instance, ranked: &[RankedMove]) -> i32 {
+    match ctx.outcome {
+        Some(outcome) => outcome_score(ctx, outcome, 0),
+        None => ranked.first().map_or(0, |r| r.score),
+    }
+}
+
+fn mark(best: i32, played: i32) -> Option<Mark> {
+    let loss = best - played;
+    if best > 0 && is_win_score(best) && !is_win_score(played) {
+        Some(Mark::MissedWin)
+    } else if loss >= BLUNDER || (is_win_score(played) && played < 0 && !is_win_score(best)) {
+        Some(Mark::Blunder)
+    } else if loss >= MISTAKE {
+        Some(Mark::Mistake)
+    } else {
+        None
+    }
+}
+
+/// Replays record and annotates each move with a search to depth
+pub fn annotate(record: &GameRecord, depth: u32) -> Vec<Annotation> {
+    let mut ctx = record.start.scratch();
+    let mut steps = vec![];
+    for m in record.moves.iter() {
+        let before = ctx.clone();
+        let missed = ctx.miss_call[&ctx.side];
+        let legal = ctx.play(*m);
+        let missed_call = legal && !missed && ctx.miss_call[&before.side];
+        steps.push((before, *m, legal, missed_call));
+    }
+    let last = ctx;
+
+    let limits = Limits {
+        depth: Some(depth),
+        ..Limits::default()
+    };
+    let ranked: Vec<Vec<RankedMove>> = steps
+        .par_iter()
+        .map(|(ctx, _, _, _)| rank_moves(ctx, &limits, None))
+        .collect();
+    let last_score = white_score(
+        last.side,
+        position_score(&last, &rank_moves(&last, &limits, None)),
+    );
+
+    steps
+        .iter()
+        .enumerate()
+        .map(|(i, (ctx, m, legal, missed_call))| {
+            let moves = &ranked[i];
+            let best = position_score(ctx, moves);
+            let after = match steps.get(i + 1) {
+                Some((next, _, _, _)) => {
+                    white_score(next.side, position_score(next, &ranked[i + 1]))
+                }
+                None => last_score,
+            };
+            let played = match moves.iter().find(|r| r.mv == *m) {
+                Some(r) => r.score,
+                // the ranked moves come from move generation, which may disagree with the
+                // rules about a move; fall back to the score after it
+                None => white_score(ctx.side, after),
+            };
+            let loss = if *legal { (best - played).max(0) } else { 0 };
+            let mark = if *legal { mark(best, played) } else { None };
+            let best_move = moves
+                .first()
+                .filter(|r| (mark.is_some() || *missed_call) && r.mv != *m && r.score > played)
+                .cloned();
+            Annotation {
+                ply: i + 1,
+                side: ctx.side,
+                mv: *m,
+                legal: *legal,
+                before: white_score(ctx.side, best),
+                after,
+                loss,
+                mark,
+                missed_call: *missed_call,
+                best: best_move,
+            }
+        })
+        .collect()
+}
+
+impl fmt::Display for Annotation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}. {} {}", self.ply, self.side, self.mv)?;
+        if !self.legal {
+            return write!(f, " rejected by the rules, skipped");
+        }
+        if let Some(mark) = self.mark {
+            write!(f, "{}", mark.symbol())?;
+        }
+        write!(
+            f,
+            " {} -> {}",
+            format_score(self.before),
+            format_score(self.after)
+        )?;
+        match self.mark {
+            Some(Mark::MissedWin) => write!(f, ", {}", Mark::MissedWin)?,
+            // a forced loss is measured in turns, not points
+            Some(mark) if is_win_score(self.after) => write!(f, ", {}", mark)?,
+            Some(mark) => write!(f, ", {} (-{})", mark, self.loss)?,
+            None => {}
+        }
+        if self.missed_call {
+            write!(f, ", missed call")?;
+        }
+        if let Some(best) = &self.best {
+            write!(f, ", better {}: {}", best.mv, format_pv(&best.pv))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,420 @@
+/*!
+Engine-vs-engine matches over the text protocol, with Elo and SPRT statistics.
+
+Each game starts two fresh engine processes in protocol mode, loads the start position with
+`l`, plays a few shared random opening turns, then asks the side to move for `go` and
+forwards every move to both engines as `m <piece> <tile>` or `c`. Games come in pairs on
+the same opening with colours swapped, so an opening that favours one side cancels out.
+
+The runner keeps its own [`Instance`] and adjudicates by the rules: a win or stalemate ends
+the game as usual, a side that makes an illegal move, answers `bestmove none` or fails to
+answer in time loses, and a game is drawn on the third repetition, at the turn limit, or
+when the side to move has nothing to play.
+
+The SPRT uses the usual normal approximation to the trinomial log-likelihood ratio of
+score between `elo0` and `elo1`.
+*/
+
+use std::{
+    collections::HashMap,
+    fmt,
+    io::{BufRead, BufReader, Write},
+    process::{Child, ChildStdin, Command, Stdio},
+    sync::mpsc::{self, Receiver},
+    thread,
+    time::Duration,
+};
+
+use crate::{
+    book,
+    game::{Instance, Outcome},
+    movegen::Move,
+    piece::Side,
+    record::GameRecord,
+    rng::Rng,
+};
+
+/// How long past its move time an engine may take to answer
+pub const GRACE: Duration = Duration::from_secs(5);
+/// How long an engine searching to a fixed depth may take to answer
+pub const DEPTH_PATIENCE: Duration = Duration::from_secs(60);
+
+/// A running engine process
+pub struct Engine {
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+}
+
+impl Engine {
+    /// Runs cmd through the shell and waits until it answers a ping
+    pub fn start(cmd: &str) -> Result<Self, &'static str> {
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg(cmd)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|_| "couldn't start engine")?;
+        let stdin = child.stdin.take().ok_or("couldn't talk to engine")?;
+        let stdout = child.stdout.take().ok_or("couldn't talk to engine")?;
+        let (tx, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                if tx.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        let mut engine = Engine {
+            child,
+            stdin,
+            lines,
+        };
+        engine.send("ping")?;
+        engine.wait_for("ok", DEPTH_PATIENCE)?;
+        Ok(engine)
+    }
+
+    pub fn send(&mut self, line: &str) -> Result<(), &'static str> {
+        writeln!(self.stdin, "{}", line)
+            .and_then(|_| self.stdin.flush())
+            .map_err(|_| "engine stopped listening")
+    }
+
+    /// Reads lines until one starts with prefix and returns the rest of it
+    fn wait_for(&mut self, prefix: &str, patience: Duration) -> Result<String, &'static str> {
+        loop {
+            let line = self
+                .lines
+                .recv_timeout(patience)
+                .map_err(|_| "engine didn't answer")?;
+            if let Some(rest) = line.trim().strip_prefix(prefix) {
+                return Ok(rest.trim().to_string());
+            }
+        }
+    }
+
+    /// Asks for a move with go and returns it, `None` if the engine has none
+    pub fn go(&mut self, go: &str, patience: Duration) -> Result<Option<Move>, &'static str> {
+        self.send(go)?;
+        match self.wait_for("bestmove", patience)?.as_str() {
+            "none" => Ok(None),
+            m => Move::decode(m).map(Some),
+        }
+    }
+
+    /// Tells the engine a move was played
+    pub fn play(&mut self, m: Move) -> Result<(), &'static str> {
+        match m {
+            Move::Step(_, _) => self.send(&format!("m {}", m)),
+            Move::Call => self.send("c"),
+        }
+    }
+}
+
+impl Drop for Engine {
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+        if !matches!(self.child.try_wait(), Ok(Some(_))) {
+            thread::sleep(Duration::from_millis(20));
+            let _ = self.child.kill();
+        }
+        let _ = self.child.wait();
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct MatchConfig {
+    pub engine_a: String,
+    pub engine_b: String,
+    pub games: usize,
+    pub depth: Option<u32>,
+    pub movetime: Option<Duration>,
+    /// random turns played before the engines take over
+    pub opening_plies: usize,
+    pub max_plies: usize,
+    pub seed: u64,
+}
+
+/// Why a game ended
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Ending {
+    Rules(Outcome),
+    /// the side lost by breaking the protocol or the rules
+    Forfeit(Side, &'static str),
+    Repetition,
+    TurnLimit,
+    NoMoves,
+}
+
+impl fmt::Display for Ending {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Ending::Rules(Outcome::Win(side, reason)) => write!(f, "{} wins by {:?}", side, reason),
+            Ending::Rules(Outcome::Stalemate) => write!(f, "stalemate"),
+            Ending::Forfeit(side, why) => write!(f, "{} forfeits, {}", side, why),
+            Ending::Repetition => write!(f, "draw by repetition"),
+            Ending::TurnLimit => write!(f, "draw at the turn limit"),
+            Ending::NoMoves => write!(f, "draw, nothing to play"),
+        }
+    }
+}
+
+impl Ending {
+    /// Returns the side that won, `None` for a draw
+    pub fn winner(&self) -> Option<Side> {
+        match self {
+            Ending::Rules(Outcome::Win(side, _)) => Some(*side),
+            Ending::Forfeit(side, _) => Some(!*side),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct MatchGame {
+    pub a_white: bool,
+    pub record: GameRecord,
+    pub ending: Ending,
+}
+
+impl MatchGame {
+    /// Score of engine A: 1 win, 0.5 draw, 0 loss
+    pub fn score_a(&self) -> f64 {
+        let a = if self.a_white {
+            Side::White
+        } else {
+            Side::Orange
+        };
+        match self.ending.winner() {
+            Some(side) if side == a => 1.0,
+            Some(_) => 0.0,
+            None => 0.5,
+        }
+    }
+}
+
+/// Plays `plies` random turns from the start position, stopping before any that ends the game
+pub fn random_opening(plies: usize, rng: &mut Rng) -> Vec<Move> {
+    let mut ctx = Instance::start();
+    let mut moves = vec![];
+    for _ in 0..plies {
+        let legal: Vec<Move> = ctx
+            .legal_moves()
+            .into_iter()
+            .filter(|m| *m != Move::Call)
+            .collect();
+        if legal.is_empty() {
+            break;
+        }
+        let m = legal[rng.below(legal.len())];
+        let mut next = ctx.clone();
+        if !next.play(m) || next.outcome.is_some() {
+            break;
+        }
+        ctx = next;
+        moves.push(m);
+    }
+    moves
+}
+
+/// Plays one game between running engines from the start position and opening
+pub fn play_game(
+    cfg: &MatchConfig,
+    white: &mut Engine,
+    orange: &mut Engine,
+    opening: &[Move],
+) -> (GameRecord, Ending) {
+    let mut ctx = Instance::start();
+    let mut record = GameRecord {
+        start: ctx.scratch(),
+        moves: vec![],
+    };
+    for (engine, owner) in [(&mut *white, Side::White), (&mut *orange, Side::Orange)] {
+        if engine.send("l").is_err() {
+            return (record, Ending::Forfeit(owner, "engine stopped listening"));
+        }
+    }
+
+    let mut go = "go".to_string();
+    if let Some(d) = cfg.depth {
+        go.push_str(&format!(" depth {}", d));
+    }
+    if let Some(t) = cfg.movetime {
+        go.push_str(&format!(" movetime {}", t.as_millis()));
+    }
+    let patience = match cfg.movetime {
+        Some(t) => t + GRACE,
+        None => DEPTH_PATIENCE,
+    };
+
+    // keyed by the book hash, which covers call and miss-call state besides the board
+    let mut seen: HashMap<u64, usize> = HashMap::new();
+    loop {
+        if let Some(outcome) = ctx.outcome {
+            return (record, Ending::Rules(outcome));
+        }
+        if record.moves.len() >= cfg.max_plies {
+            return (record, Ending::TurnLimit);
+        }
+        let count = seen.entry(book::hash(&ctx)).or_insert(0);
+        *count += 1;
+        if *count >= 3 {
+            return (record, Ending::Repetition);
+        }
+        if ctx.legal_moves().is_empty() {
+            return (record, Ending::NoMoves);
+        }
+
+        let side = ctx.side;
+        let m = match opening.get(record.moves.len()) {
+            Some(m) => *m,
+            None => {
+                let engine = match side {
+                    Side::White => &mut *white,
+                    Side::Orange => &mut *orange,
+                };
+                match engine.go(&go, patience) {
+                    Ok(Some(m)) => m,
+                    Ok(None) => return (record, Ending::Forfeit(side, "no move")),
+                    Err(e) => return (record, Ending::Forfeit(side, e)),
+                }
+            }
+        };
+        if !ctx.legal_moves().contains(&m) || !ctx.play(m) {
+            return (record, Ending::Forfeit(side, "illegal move"));
+        }
+        record.moves.push(m);
+        for (engine, owner) in [(&mut *white, Side::White), (&mut *orange, Side::Orange)] {
+            if engine.play(m).is_err() {
+                return (record, Ending::Forfeit(owner, "engine stopped listening"));
+            }
+        }
+    }
+}
+
+/// Elo difference for a score share, infinite at 0 and 1
+pub fn elo(score: f64) -> f64 {
+    400.0 * (score / (1.0 - score)).log10()
+}
+
+/// Expected score for an Elo difference
+pub fn expected_score(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+/// Wins, draws and losses of engine A
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Wdl {
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+}
+
+impl Wdl {
+    pub fn add(&mut self, score: f64) {
+        if score == 1.0 {
+            self.wins += 1;
+        } else if score == 0.0 {
+            self.losses += 1;
+        } else {
+            self.draws += 1;
+        }
+    }
+
+    pub fn games(&self) -> usize {
+        self.wins + self.draws + self.losses
+    }
+
+    /// Returns mean score and its per-game variance
+    fn moments(&self) -> (f64, f64) {
+        let n = self.games().max(1) as f64;
+        let (w, d, l) = (self.wins as f64, self.draws as f64, self.losses as f64);
+        let score = (w + d / 2.0) / n;
+        let var = (w * (1.0 - score).powi(2) + d * (0.5 - score).powi(2) + l * score.powi(2)) / n;
+        // a perfect score has no variance; count it as half a game short of perfect
+        let edge = 1.0 / (2.0 * n);
+        (score.clamp(edge, 1.0 - edge), var.max(edge * (1.0 - edge)))
+    }
+
+    /// Returns Elo difference of A over B and the half width of its 95% interval
+    pub fn elo(&self) -> (f64, f64) {
+        let (score, var) = self.moments();
+        let margin = 1.96 * (var / self.games().max(1) as f64).sqrt();
+        let low = elo((score - margin).max(1e-3));
+        let high = elo((score + margin).min(1.0 - 1e-3));
+        (elo(score), (high - low) / 2.0)
+    }
+
+    /// Log-likelihood ratio of elo1 against elo0
+    pub fn llr(&self, elo0: f64, elo1: f64) -> f64 {
+        if self.games() == 0 {
+            return 0.0;
+        }
+        let (score, var) = self.moments();
+        let (s0, s1) = (expected_score(elo0), expected_score(elo1));
+        (s1 - s0) * (2.0 * score - s0 - s1) / (2.0 * var) * self.games() as f64
+    }
+}
+
+/// Sequential probability ratio test of `elo1` against `elo0`
+#[derive(Clone, Copy, Debug)]
+pub struct Sprt {
+    pub elo0: f64,
+    pub elo1: f64,
+    pub alpha: f64,
+    pub beta: f64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SprtResult {
+    /// A is at least elo1 stronger
+    H1,
+    /// A is at most elo0 stronger
+    H0,
+    Continue,
+}
+
+impl Sprt {
+    /// Returns the lower and upper LLR bounds
+    pub fn bounds(&self) -> (f64, f64) {
+        (
+            (self.beta / (1.0 - self.alpha)).ln(),
+            ((1.0 - self.beta) / self.alpha).ln(),
+        )
+    }
+
+    pub fn test(&self, wdl: &Wdl) -> (f64, SprtResult) {
+        let llr = wdl.llr(self.elo0, self.elo1);
+        let (lower, upper) = self.bounds();
+        let result = if llr >= upper {
+            SprtResult::H1
+        } else if llr <= lower {
+            SprtResult::H0
+        } else {
+            SprtResult::Continue
+        };
+        (llr, result)
+    }
+}
+
+/// Plays game number i of a match: openings are shared by pairs, A is white in even games
+pub fn play_match_game(cfg: &MatchConfig, i: usize) -> Result<MatchGame, &'static str> {
+    let mut rng = Rng::new(cfg.seed ^ (i as u64 / 2 + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+    let opening = random_opening(cfg.opening_plies, &mut rng);
+    let a_white = i.is_multiple_of(2);
+    let mut a = Engine::start(&cfg.engine_a)?;
+    let mut b = Engine::start(&cfg.engine_b)?;
+    let (record, ending) = if a_white {
+        play_game(cfg, &mut a, &mut b, &opening)
+    } else {
+        play_game(cfg, &mut b, &mut a, &opening)
+    };
+    Ok(MatchGame {
+        a_white,
+        record,
+        ending,
+    })
+}
@@ -0,0 +1,79 @@
+/*!
+Rule checks over many positions at once, spread over all cores.
+
+Input is one position per line in state file header format (`<tiles> [side]`, side
+defaults to White). Blank lines and lines starting with `#` are skipped.
+*/
+
+use std::fmt;
+
+use rayon::prelude::*;
+
+use crate::{
+    board::Board,
+    game::{Instance, WinReason},
+    piece::Side,
+};
+
+/// Rule check results for one position
+#[derive(Clone, Debug)]
+pub struct Report {
+    pub tiles: String,
+    pub side: Side,
+    /// win available to the side to move, whether or not it called
+    pub win: Option<WinReason>,
+    /// `has_win` for the side to move, false unless it has called
+    pub has_win: bool,
+    pub stalemate: bool,
+    pub moves: usize,
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let win = match self.win {
+            Some(WinReason::EdgeAlignment) => "edge",
+            Some(WinReason::PassiveElimination) => "passive",
+            Some(WinReason::Timeout) => "time",
+            None => "none",
+        };
+        write!(
+            f,
+            "{} {} win={} has_win={} stalemate={} moves={}",
+            self.tiles, self.side, win, self.has_win, self.stalemate, self.moves
+        )
+    }
+}
+
+/// Parses position line and runs the rule checks on it
+pub fn check(line: &str) -> Result<Report, &'static str> {
+    let parts = line.split_whitespace().collect::<Vec<&str>>();
+    let tiles = parts.first().ok_or("empty position")?;
+    let mut ctx = Instance::blank();
+    ctx.board = Board::decode(tiles.to_string())?;
+    if let Some(s) = parts.get(1) {
+        ctx.side = s.parse::<Side>().map_err(|_| "invalid side")?;
+    }
+
+    let win = ctx.win_reason();
+    let stalemate = ctx.has_stalemate();
+    let moves = ctx.legal_moves().len();
+    Ok(Report {
+        tiles: ctx.board.encode(),
+        side: ctx.side,
+        win,
+        has_win: ctx.has_win(),
+        stalemate,
+        moves,
+    })
+}
+
+/// Checks every position line in parallel. Results keep the input order and skipped lines
+/// are left out.
+pub fn check_all(lines: &[String]) -> Vec<(usize, Result<Report, &'static str>)> {
+    lines
+        .par_iter()
+        .enumerate()
+        .filter(|(_, l)| !l.trim().is_empty() && !l.trim_start().starts_with('#'))
+        .map(|(n, l)| (n + 1, check(l)))
+        .collect()
+}
@@ -1,3 +1,5 @@
+use alloc::string::{String, ToString};
+
 use crate::position::{Normalizable, Position};
 use bitvec::prelude::*;
 
@@ -42,7 +44,7 @@ impl BitBoard {
     }
 
     /// fill range of normalized positions
-    pub fn fill_range(&mut self, r: std::ops::Range<u8>) -> BitBoard {
+    pub fn fill_range(&mut self, r: core::ops::Range<u8>) -> BitBoard {
         for i in r {
             self.num.set(i.into(), true);
         }
@@ -1,3 +1,6 @@
+use alloc::string::{String, ToString};
+use alloc::{vec, vec::Vec};
+
 use crate::bitboard::BitBoard;
 use crate::piece::*;
 use crate::position::{Normalizable, Position};
@@ -86,7 +89,7 @@ impl Board {
                     let (p, side) = dec.unwrap();
 
                     g.side_bitboard(side).num.set(norm, true);
-                    g.piece_bitboard(p).unwrap().num.set(norm, true);
+                    g.piece_bitboard(p)?.num.set(norm, true);
                     norm += 1;
                 }
             }
@@ -158,6 +161,34 @@ impl Board {
         }
     }
 
+    /// Places piece at normalized position without checking the tile is empty
+    pub fn put(&mut self, p: Piece, i: usize) -> Result<(), &'static str> {
+        let side = match p.side() {
+            Some(s) => s,
+            None => return Err("Invalid piece"),
+        };
+        if i >= 64 {
+            return Err("invalid position");
+        }
+        self.side_bitboard(side).num.set(i, true);
+        self.piece_bitboard(p)?.num.set(i, true);
+        Ok(())
+    }
+
+    /// Takes piece off normalized position
+    pub fn remove(&mut self, p: Piece, i: usize) -> Result<(), &'static str> {
+        let side = match p.side() {
+            Some(s) => s,
+            None => return Err("Invalid piece"),
+        };
+        if i >= 64 {
+            return Err("invalid position");
+        }
+        self.side_bitboard(side).num.set(i, false);
+        self.piece_bitboard(p)?.num.set(i, false);
+        Ok(())
+    }
+
     /// Finds piece non-normalized position on board
     pub fn pos_from_piece(&mut self, p: Piece) -> Result<Position, &'static str> {
         let p_dec = Piece::decode(p.encode());
@@ -217,6 +248,7 @@ impl Board {
     }
 
     /// prints ascii representation of board to stdout
+    #[cfg(feature = "std")]
     pub fn print_board(self) {
         print!("  ");
         for i in 0..8 {
@@ -263,23 +295,19 @@ impl Board {
         match p {
             Piece::None => Ok(bitb),
             Piece::Bird(s) => {
-                let mut consume_mask: BitBoard = BitBoard::new();
-                match s {
+                // only opponent pieces can be taken
+                let consume_mask = match s {
                     Side::Orange => {
                         let mask = bitb.set((pos.0, pos.1 - 1)).set((pos.0 - 1, pos.1 - 1));
-                        if self.white.num.data & mask.num.data != 0 {
-                            consume_mask = BitBoard::from_bitarray(
-                                (self.board_state().data & mask.num.data).into_bitarray::<Msb0>(),
-                            );
-                        }
+                        BitBoard::from_bitarray(
+                            (self.white.num.data & mask.num.data).into_bitarray::<Msb0>(),
+                        )
                     }
                     Side::White => {
                         let mask = bitb.set((pos.0, pos.1 + 1)).set((pos.0 - 1, pos.1 + 1));
-                        if self.orange.num.data & mask.num.data != 0 {
-                            consume_mask = BitBoard::from_bitarray(
-                                (self.board_state().data & mask.num.data).into_bitarray::<Msb0>(),
-                            );
-                        }
+                        BitBoard::from_bitarray(
+                            (self.orange.num.data & mask.num.data).into_bitarray::<Msb0>(),
+                        )
                     }
                 };
                 let mut base = BitBoard::new();
@@ -328,43 +356,44 @@ impl Board {
                         .into_bitarray(),
                 ))
             }
-            Piece::Sloth(_) => Ok(BitBoard::from_bitarray(
-                (!self.board_state().data
-                    & bitb
-                        .set((pos.0 + 1, pos.1))
-                        .set((pos.0 - 1, pos.1))
-                        .set((pos.0, pos.1 + 1))
-                        .set((pos.0, pos.1 - 1))
-                        .num
-                        .data)
-                    .into_bitarray(),
-            )),
+            Piece::Sloth(_) => {
+                // a column past 8 would wrap onto the next row
+                if pos.0 < 8 {
+                    bitb.set((pos.0 + 1, pos.1));
+                }
+                Ok(BitBoard::from_bitarray(
+                    (!self.board_state().data
+                        & bitb
+                            .set((pos.0 - 1, pos.1))
+                            .set((pos.0, pos.1 + 1))
+                            .set((pos.0, pos.1 - 1))
+                            .num
+                            .data)
+                        .into_bitarray(),
+                ))
+            }
             Piece::Tiger(s) | Piece::Otter(s) | Piece::MantisShrimp(s) | Piece::Snake(s) => {
-                let mut consume_mask: BitBoard = BitBoard::new();
-                match s {
+                // only opponent pieces can be taken
+                let consume_mask = match s {
                     Side::Orange => {
                         let mask = bitb.set((pos.0, pos.1 - 1)).set((pos.0 - 1, pos.1 - 1));
-                        if self.white.num.data & mask.num.data != 0 {
-                            consume_mask = BitBoard::from_bitarray(
-                                (self.board_state().data & mask.num.data).into_bitarray::<Msb0>(),
-                            );
-                        }
+                        BitBoard::from_bitarray(
+                            (self.white.num.data & mask.num.data).into_bitarray::<Msb0>(),
+                        )
                     }
                     Side::White => {
                         let mask = bitb.set((pos.0, pos.1 + 1)).set((pos.0 - 1, pos.1 + 1));
-                        if self.orange.num.data & mask.num.data != 0 {
-                            consume_mask = BitBoard::from_bitarray(
-                                (self.board_state().data & mask.num.data).into_bitarray::<Msb0>(),
-                            );
-                        }
+                        BitBoard::from_bitarray(
+                            (self.orange.num.data & mask.num.data).into_bitarray::<Msb0>(),
+                        )
                     }
                 };
                 let mut base = BitBoard::new();
-                let base_mask = base
-                    .set((pos.0 - 1, pos.1 + 1))
-                    .set((pos.0 + 1, pos.1 + 1))
-                    .set((pos.0 + 1, pos.1 - 1))
-                    .set((pos.0 - 1, pos.1 - 1));
+                if pos.0 < 8 {
+                    base.set((pos.0 + 1, pos.1 + 1));
+                    base.set((pos.0 + 1, pos.1 - 1));
+                }
+                let base_mask = base.set((pos.0 - 1, pos.1 + 1)).set((pos.0 - 1, pos.1 - 1));
 
                 Ok(BitBoard::from_bitarray(
                     ((!self.board_state().data & base_mask.num.data) | consume_mask.num.data)
@@ -388,7 +417,7 @@ impl Board {
             let norm_enc = self.piece_from_norm(target_norm as u64).encode();
             let target_enc = Piece::decode(norm_enc);
             if target_enc.is_err() {
-                println!("{}", target_enc.unwrap_err());
+                report!("{}", target_enc.unwrap_err());
                 return;
             }
             let (target_piece, side) = target_enc.unwrap();
@@ -398,14 +427,14 @@ impl Board {
         }
         let p_enc = Piece::decode(p.encode());
         if p_enc.is_err() {
-            println!("{}", p_enc.unwrap_err());
+            report!("{}", p_enc.unwrap_err());
             return;
         }
         let (_, act_side) = p_enc.unwrap();
 
         let act_pos = self.pos_from_piece(p);
         if act_pos.is_err() {
-            println!("{}", act_pos.unwrap_err());
+            report!("{}", act_pos.unwrap_err());
             return;
         }
         self.side_bitboard(act_side).set(act_pos.unwrap());
@@ -430,7 +459,7 @@ impl Board {
         };
 
         if target_bitb.num.data & valid_bitb.num.data == 0 {
-            println!("Invalid miss-call position");
+            report!("Invalid miss-call position");
             return false;
         } else {
             self.new_position_unsafe(p, to);
@@ -444,25 +473,25 @@ impl Board {
         target_bitb.set(to);
         let mmask = self.move_mask_raw(p);
         if mmask.is_err() {
-            println!("{}", mmask.unwrap_err());
+            report!("{}", mmask.unwrap_err());
             return false;
         }
 
         if mmask.unwrap().num.data & target_bitb.num.data == 0 {
             if to.encode().is_err() {
-                println!("{}", to.encode().unwrap_err());
+                report!("{}", to.encode().unwrap_err());
                 return false;
             }
             let p_place = self.pos_from_piece(p);
             if p_place.is_err() {
-                println!("{}", p_place.unwrap_err());
+                report!("{}", p_place.unwrap_err());
                 return false;
             }
             if p_place.unwrap().encode().is_err() {
-                println!("{}", p_place.unwrap().encode().unwrap_err());
+                report!("{}", p_place.unwrap().encode().unwrap_err());
                 return false;
             }
-            println!(
+            report!(
                 "Not valid move: {}{}-{}{}",
                 p.encode(),
                 p_place.unwrap().encode().unwrap(),
@@ -0,0 +1,260 @@
+/*!
+Opening book: weighted moves keyed by a Zobrist hash of the position.
+
+The hash covers the board, side to move and call state. Its keys come from [`Rng`] with a
+fixed seed, so the same position hashes the same everywhere and books can be shared.
+
+Books are built from games. Each move in a game's first plies adds to its weight: two if
+the side that played it went on to win, one if the game was drawn or unfinished, and
+nothing if it lost.
+
+A book file is `GTCO`, the entry count (u32), then entries sorted by hash, each the hash
+(u64), the move (u16) and its weight (u32), little endian. A move is `tile << 4 | side << 3
+| kind` for a step, and `0xffff` for a call.
+*/
+
+use std::{cmp::Reverse, collections::HashMap, fs, sync::OnceLock};
+
+use crate::{
+    board::Board,
+    game::{Instance, Outcome},
+    movegen::Move,
+    piece::{Piece, Side},
+    position::Normalizable,
+    record::GameRecord,
+    rng::Rng,
+};
+
+/// Plies of each game added to a book when none is given
+pub const DEFAULT_PLIES: usize = 16;
+
+const MAGIC: &[u8; 4] = b"GTCO";
+const ENTRY_SIZE: usize = 14;
+const CALL: u16 = 0xffff;
+
+struct Keys {
+    /// one key per piece (`side << 3 | kind`) and tile
+    tiles: [[u64; 64]; 16],
+    white_to_move: u64,
+    /// white call, orange call, white miss-call, orange miss-call
+    flags: [u64; 4],
+}
+
+fn keys() -> &'static Keys {
+    static KEYS: OnceLock<Keys> = OnceLock::new();
+    KEYS.get_or_init(|| {
+        let mut rng = Rng::new(0x6774_635f_626f_6f6b);
+        let mut tiles = [[0u64; 64]; 16];
+        for piece in tiles.iter_mut() {
+            for key in piece.iter_mut() {
+                *key = rng.next_u64();
+            }
+        }
+        Keys {
+            tiles,
+            white_to_move: rng.next_u64(),
+            flags: [
+                rng.next_u64(),
+                rng.next_u64(),
+                rng.next_u64(),
+                rng.next_u64(),
+            ],
+        }
+    })
+}
+
+fn side_bit(s: Side) -> u16 {
+    match s {
+        Side::Orange => 0,
+        Side::White => 1,
+    }
+}
+
+/// Returns Zobrist hash of the board, side to move and call state of ctx
+pub fn hash(ctx: &Instance) -> u64 {
+    let keys = keys();
+    let board = ctx.board;
+    let mut h = 0;
+    for i in board.board_state().iter_ones() {
+        let p = board.piece_from_norm(i as u64);
+        if let (Some(kind), Some(side)) = (p.index(), p.side()) {
+            h ^= keys.tiles[(side_bit(side) << 3 | kind as u16) as usize][i];
+        }
+    }
+    if ctx.side == Side::White {
+        h ^= keys.white_to_move;
+    }
+    let flags = [
+        ctx.call[&Side::White],
+        ctx.call[&Side::Orange],
+        ctx.miss_call[&Side::White],
+        ctx.miss_call[&Side::Orange],
+    ];
+    for (key, set) in keys.flags.iter().zip(flags) {
+        if set {
+            h ^= key;
+        }
+    }
+    h
+}
+
+fn encode_move(m: Move) -> u16 {
+    match m {
+        Move::Call => CALL,
+        Move::Step(p, pos) => {
+            let kind = p.index().unwrap_or_default() as u16;
+            let side = side_bit(p.side().unwrap_or_default());
+            (pos.normal() as u16) << 4 | side << 3 | kind
+        }
+    }
+}
+
+fn decode_move(v: u16) -> Result<Move, &'static str> {
+    if v == CALL {
+        return Ok(Move::Call);
+    }
+    let side = if v >> 3 & 1 == 1 {
+        Side::White
+    } else {
+        Side::Orange
+    };
+    let tile = (v >> 4) as u64;
+    if tile >= 64 {
+        return Err("invalid book move");
+    }
+    Ok(Move::Step(
+        Piece::from_index((v & 0b111) as u8, side),
+        Board::normal_to_pos(tile),
+    ))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BookMove {
+    pub mv: Move,
+    pub weight: u32,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Book {
+    entries: HashMap<u64, Vec<BookMove>>,
+}
+
+impl Book {
+    pub fn new() -> Self {
+        Book::default()
+    }
+
+    /// Number of positions in the book
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Adds weight to move m in the position of ctx
+    pub fn add(&mut self, ctx: &Instance, m: Move, weight: u32) {
+        let moves = self.entries.entry(hash(ctx)).or_default();
+        match moves.iter_mut().find(|b| b.mv == m) {
+            Some(b) => b.weight += weight,
+            None => moves.push(BookMove { mv: m, weight }),
+        }
+    }
+
+    /// Adds the first plies of a game, weighted by how it ended for each move's side
+    pub fn add_game(&mut self, record: &GameRecord, plies: usize) {
+        let result = record.replay().last().and_then(|p| p.outcome);
+        for (ctx, m) in record.steps().iter().take(plies) {
+            let weight = match result {
+                Some(Outcome::Win(side, _)) if side == ctx.side => 2,
+                Some(Outcome::Win(_, _)) => 0,
+                _ => 1,
+            };
+            if weight > 0 {
+                self.add(ctx, *m, weight);
+            }
+        }
+    }
+
+    /// Returns book moves for the position of ctx that are legal there, heaviest first
+    pub fn moves(&self, ctx: &Instance) -> Vec<BookMove> {
+        let legal = ctx.legal_moves();
+        let mut moves: Vec<BookMove> = self
+            .entries
+            .get(&hash(ctx))
+            .map(|m| {
+                m.iter()
+                    .filter(|b| legal.contains(&b.mv))
+                    .copied()
+                    .collect()
+            })
+            .unwrap_or_default();
+        moves.sort_by_key(|b| Reverse(b.weight));
+        moves
+    }
+
+    /// Picks a book move at random in proportion to the weights
+    pub fn pick(&self, ctx: &Instance, rng: &mut Rng) -> Option<Move> {
+        let moves = self.moves(ctx);
+        let total: u64 = moves.iter().map(|b| b.weight as u64).sum();
+        if total == 0 {
+            return None;
+        }
+        let mut at = rng.next_u64() % total;
+        for b in moves {
+            if at < b.weight as u64 {
+                return Some(b.mv);
+            }
+            at -= b.weight as u64;
+        }
+        None
+    }
+
+    pub fn parse(raw: &[u8]) -> Result<Self, &'static str> {
+        if raw.len() < 8 || &raw[0..4] != MAGIC {
+            return Err("not a book file");
+        }
+        let count = u32::from_le_bytes([raw[4], raw[5], raw[6], raw[7]]) as usize;
+        if raw.len() != 8 + count * ENTRY_SIZE {
+            return Err("book file has the wrong size");
+        }
+        let mut book = Book::new();
+        for e in raw[8..].chunks_exact(ENTRY_SIZE) {
+            let mut key = [0u8; 8];
+            key.copy_from_slice(&e[0..8]);
+            let mv = decode_move(u16::from_le_bytes([e[8], e[9]]))?;
+            let weight = u32::from_le_bytes([e[10], e[11], e[12], e[13]]);
+            book.entries
+                .entry(u64::from_le_bytes(key))
+                .or_default()
+                .push(BookMove { mv, weight });
+        }
+        Ok(book)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut keys: Vec<&u64> = self.entries.keys().collect();
+        keys.sort();
+        let count: usize = self.entries.values().map(|m| m.len()).sum();
+        let mut out = MAGIC.to_vec();
+        out.extend((count as u32).to_le_bytes());
+        for key in keys {
+            for b in self.entries[key].iter() {
+                out.extend(key.to_le_bytes());
+                out.extend(encode_move(b.mv).to_le_bytes());
+                out.extend(b.weight.to_le_bytes());
+            }
+        }
+        out
+    }
+
+    pub fn load(path: &str) -> Result<Self, &'static str> {
+        let raw = fs::read(path).map_err(|_| "Couldn't read book file.")?;
+        Book::parse(&raw)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), &'static str> {
+        fs::write(path, self.to_bytes()).map_err(|_| "Failed to write book.")
+    }
+}
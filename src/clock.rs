@@ -0,0 +1,109 @@
+/*!
+Game clocks: a base time per side plus an increment added after each of its turns.
+
+Only the side to move's clock runs. A side whose clock runs out loses the game on time the
+next time the instance checks, see [`crate::game::Instance::flag_fall`].
+*/
+
+use std::time::{Duration, Instant};
+
+use crate::piece::Side;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Clock {
+    pub remaining: Duration,
+    pub increment: Duration,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Clocks {
+    pub white: Clock,
+    pub orange: Clock,
+    /// when the running turn started, `None` while the clocks are stopped
+    turn_start: Option<Instant>,
+}
+
+impl Clocks {
+    /// Returns stopped clocks with the same time for both sides
+    pub fn new(base: Duration, increment: Duration) -> Self {
+        let clock = Clock {
+            remaining: base,
+            increment,
+        };
+        Clocks {
+            white: clock,
+            orange: clock,
+            turn_start: None,
+        }
+    }
+
+    pub fn get(&self, side: Side) -> &Clock {
+        match side {
+            Side::White => &self.white,
+            Side::Orange => &self.orange,
+        }
+    }
+
+    pub fn get_mut(&mut self, side: Side) -> &mut Clock {
+        match side {
+            Side::White => &mut self.white,
+            Side::Orange => &mut self.orange,
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.turn_start.is_some()
+    }
+
+    /// Starts the running turn now
+    pub fn start(&mut self) {
+        self.turn_start = Some(Instant::now());
+    }
+
+    /// Charges the running turn to the side to move and stops the clocks
+    pub fn stop(&mut self, to_move: Side) {
+        if let Some(start) = self.turn_start.take() {
+            let clock = self.get_mut(to_move);
+            clock.remaining = clock.remaining.saturating_sub(start.elapsed());
+        }
+    }
+
+    /// Time side has left, counting the running turn if it is side's
+    pub fn remaining(&self, side: Side, to_move: Side) -> Duration {
+        let clock = self.get(side);
+        match self.turn_start {
+            Some(start) if side == to_move => clock.remaining.saturating_sub(start.elapsed()),
+            _ => clock.remaining,
+        }
+    }
+
+    /// Sets time side has left. The running turn starts over if it is side's.
+    pub fn set_remaining(&mut self, side: Side, to_move: Side, remaining: Duration) {
+        self.get_mut(side).remaining = remaining;
+        if side == to_move && self.is_running() {
+            self.start();
+        }
+    }
+
+    /// Returns true if the side to move is out of time
+    pub fn flagged(&self, to_move: Side) -> bool {
+        self.remaining(to_move, to_move).is_zero()
+    }
+
+    /// Charges the running turn to mover, adds its increment and starts the opponent's turn
+    pub fn end_turn(&mut self, mover: Side) {
+        if !self.is_running() {
+            return;
+        }
+        self.stop(mover);
+        let clock = self.get_mut(mover);
+        clock.remaining += clock.increment;
+        self.start();
+    }
+}
+
+/// Formats duration as `m:ss.t`
+pub fn format_duration(d: Duration) -> String {
+    let tenths = d.as_millis() / 100;
+    format!("{}:{:02}.{}", tenths / 600, tenths / 10 % 60, tenths % 10)
+}
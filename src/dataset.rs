@@ -0,0 +1,175 @@
+/*!
+Training positions labelled with a search score and the game result.
+
+A record is [`RECORD_SIZE`] bytes: the [`PackedPosition`], the search score for the side to
+move (i16, little endian, clamped) and one result byte:
+
+- 0: game unfinished
+- 1: stalemate
+- 2/3: white won by edge alignment/passive elimination
+- 4/5: orange won by edge alignment/passive elimination
+- 6/7: white/orange won on time
+*/
+
+use std::io::{self, Read, Write};
+
+use rayon::prelude::*;
+
+use crate::{
+    game::{Instance, Outcome, WinReason},
+    pack::{PackedPosition, PACKED_SIZE},
+    piece::Side,
+    record::GameRecord,
+    search::{search, Limits},
+};
+
+pub const RECORD_SIZE: usize = PACKED_SIZE + 3;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TrainingRecord {
+    pub position: PackedPosition,
+    pub score: i16,
+    pub result: Option<Outcome>,
+}
+
+fn result_byte(result: Option<Outcome>) -> u8 {
+    match result {
+        None => 0,
+        Some(Outcome::Stalemate) => 1,
+        Some(Outcome::Win(side, reason)) => {
+            let orange = (side == Side::Orange) as u8;
+            match reason {
+                WinReason::EdgeAlignment => 2 + orange * 2,
+                WinReason::PassiveElimination => 3 + orange * 2,
+                WinReason::Timeout => 6 + orange,
+            }
+        }
+    }
+}
+
+fn byte_result(b: u8) -> Result<Option<Outcome>, &'static str> {
+    let reason = |b: u8| match b % 2 {
+        0 => WinReason::EdgeAlignment,
+        _ => WinReason::PassiveElimination,
+    };
+    match b {
+        0 => Ok(None),
+        1 => Ok(Some(Outcome::Stalemate)),
+        2 | 3 => Ok(Some(Outcome::Win(Side::White, reason(b)))),
+        4 | 5 => Ok(Some(Outcome::Win(Side::Orange, reason(b)))),
+        6 => Ok(Some(Outcome::Win(Side::White, WinReason::Timeout))),
+        7 => Ok(Some(Outcome::Win(Side::Orange, WinReason::Timeout))),
+        _ => Err("invalid result byte"),
+    }
+}
+
+impl TrainingRecord {
+    /// Labels position with a search score, clamped to fit an i16
+    pub fn new(ctx: &Instance, score: i32, result: Option<Outcome>) -> Result<Self, &'static str> {
+        Ok(TrainingRecord {
+            position: PackedPosition::encode(ctx)?,
+            score: score.clamp(i16::MIN as i32 + 1, i16::MAX as i32) as i16,
+            result,
+        })
+    }
+
+    pub fn to_bytes(&self) -> [u8; RECORD_SIZE] {
+        let mut out = [0u8; RECORD_SIZE];
+        out[..PACKED_SIZE].copy_from_slice(&self.position.0);
+        out[PACKED_SIZE..PACKED_SIZE + 2].copy_from_slice(&self.score.to_le_bytes());
+        out[PACKED_SIZE + 2] = result_byte(self.result);
+        out
+    }
+
+    pub fn from_bytes(bytes: &[u8; RECORD_SIZE]) -> Result<Self, &'static str> {
+        let mut position = [0u8; PACKED_SIZE];
+        position.copy_from_slice(&bytes[..PACKED_SIZE]);
+        Ok(TrainingRecord {
+            position: PackedPosition(position),
+            score: i16::from_le_bytes([bytes[PACKED_SIZE], bytes[PACKED_SIZE + 1]]),
+            result: byte_result(bytes[PACKED_SIZE + 2])?,
+        })
+    }
+}
+
+/// Writes training records back to back
+pub struct RecordWriter<W: Write> {
+    inner: W,
+}
+
+impl<W: Write> RecordWriter<W> {
+    pub fn new(inner: W) -> Self {
+        RecordWriter { inner }
+    }
+
+    pub fn write(&mut self, r: &TrainingRecord) -> io::Result<()> {
+        self.inner.write_all(&r.to_bytes())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Reads training records until the end of the stream
+pub struct RecordReader<R: Read> {
+    inner: R,
+}
+
+impl<R: Read> RecordReader<R> {
+    pub fn new(inner: R) -> Self {
+        RecordReader { inner }
+    }
+}
+
+impl<R: Read> Iterator for RecordReader<R> {
+    type Item = io::Result<TrainingRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut buf = [0u8; RECORD_SIZE];
+        let mut filled = 0;
+        while filled < RECORD_SIZE {
+            match self.inner.read(&mut buf[filled..]) {
+                Ok(0) if filled == 0 => return None,
+                Ok(0) => {
+                    return Some(Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "truncated training record",
+                    )))
+                }
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Some(Err(e)),
+            }
+        }
+        Some(
+            TrainingRecord::from_bytes(&buf)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+        )
+    }
+}
+
+/// Searches every position of saved games to depth and labels it with the score and the
+/// final result of its game. Finished positions and forced moves are left out.
+pub fn label_games(records: &[GameRecord], depth: u32) -> Vec<TrainingRecord> {
+    let limits = Limits {
+        depth: Some(depth),
+        ..Limits::default()
+    };
+    records
+        .par_iter()
+        .flat_map_iter(|r| {
+            let positions = r.replay();
+            let result = positions.last().and_then(|p| p.outcome);
+            positions
+                .into_iter()
+                // a lone legal move is played without searching, so it has no score
+                .filter(|p| p.outcome.is_none() && p.legal_moves().len() > 1)
+                .filter_map(|p| {
+                    let score = search(&p, &limits, |_| {}).score;
+                    TrainingRecord::new(&p, score, result).ok()
+                })
+                .collect::<Vec<TrainingRecord>>()
+        })
+        .collect()
+}
@@ -0,0 +1,244 @@
+/*!
+Handcrafted static evaluation.
+
+Each term is counted for both sides and scored as side to move minus opponent:
+
+- material: passive and aggressive pieces on the board
+- edge files: passives lined up on an edge-alignment file (`GOAT_E_NUMBER`,
+  `SLOTH_E_NUMBER`) the opponent isn't blocking, squared since four of them win
+- edge approach: passives close to an edge file but not on it yet
+- blockers: edge files with any own piece on them, since one is enough to block the file,
+  each worth more the more opponent passives it is holding off
+- mobility: piece steps from `move_mask_raw`
+- passive threat: opponent passives that can be taken next move, plus a bonus once the
+  opponent is down to its last passive
+
+Term weights live in [`Weights`]. A search takes them from its
+[`Limits`](crate::search::Limits), where they default to the hand-picked values and can be
+replaced by weights fitted with `gtc tune`.
+*/
+
+use std::{fmt, fs};
+
+use bitvec::{prelude::Msb0, view::BitViewSized};
+
+use crate::{
+    board::Board,
+    game::{Instance, GOAT_E_NUMBER, SLOTH_E_NUMBER},
+    piece::{Piece, Side},
+};
+
+/// Term names in the order of [`Terms::values`] and [`Weights::values`]
+pub const NAMES: [&str; 7] = [
+    "passives",
+    "aggressives",
+    "edge files",
+    "edge approach",
+    "blockers",
+    "mobility",
+    "passive threat",
+];
+
+/// Weight per term count
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Weights {
+    pub passive: f64,
+    pub aggressive: f64,
+    pub edge_file: f64,
+    pub edge_approach: f64,
+    pub blocker: f64,
+    pub mobility: f64,
+    pub passive_threat: f64,
+}
+
+impl Default for Weights {
+    fn default() -> Self {
+        Weights {
+            passive: 100.0,
+            aggressive: 60.0,
+            edge_file: 15.0,
+            edge_approach: 5.0,
+            blocker: 20.0,
+            mobility: 3.0,
+            passive_threat: 25.0,
+        }
+    }
+}
+
+impl Weights {
+    pub fn values(&self) -> [f64; 7] {
+        [
+            self.passive,
+            self.aggressive,
+            self.edge_file,
+            self.edge_approach,
+            self.blocker,
+            self.mobility,
+            self.passive_threat,
+        ]
+    }
+
+    pub fn from_values(v: [f64; 7]) -> Self {
+        Weights {
+            passive: v[0],
+            aggressive: v[1],
+            edge_file: v[2],
+            edge_approach: v[3],
+            blocker: v[4],
+            mobility: v[5],
+            passive_threat: v[6],
+        }
+    }
+
+    /// Parses weights file: one `<term name>: <weight>` per line. Missing terms keep
+    /// their default weight.
+    pub fn parse(raw: &str) -> Result<Self, &'static str> {
+        let mut v = Weights::default().values();
+        for line in raw.lines() {
+            if line.trim().is_empty() || line.trim_start().starts_with('#') {
+                continue;
+            }
+            let (name, value) = line.split_once(':').ok_or("invalid weights line")?;
+            let i = NAMES
+                .iter()
+                .position(|n| *n == name.trim())
+                .ok_or("unknown weight name")?;
+            v[i] = value.trim().parse::<f64>().map_err(|_| "invalid weight")?;
+        }
+        Ok(Weights::from_values(v))
+    }
+
+    pub fn load(path: &str) -> Result<Self, &'static str> {
+        let raw = fs::read_to_string(path).map_err(|_| "Couldn't read weights file.")?;
+        Weights::parse(raw.as_str())
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), &'static str> {
+        fs::write(path, self.to_string()).map_err(|_| "Failed to write weights.")
+    }
+}
+
+impl fmt::Display for Weights {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, value) in NAMES.iter().zip(self.values()) {
+            writeln!(f, "{}: {:.3}", name, value)?;
+        }
+        Ok(())
+    }
+}
+
+/// Raw term counts for one side
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Terms {
+    pub passives: i32,
+    pub aggressives: i32,
+    pub edge_files: i32,
+    pub edge_approach: i32,
+    pub blockers: i32,
+    pub mobility: i32,
+    pub passive_threat: i32,
+}
+
+impl Terms {
+    pub fn values(&self) -> [i32; 7] {
+        [
+            self.passives,
+            self.aggressives,
+            self.edge_files,
+            self.edge_approach,
+            self.blockers,
+            self.mobility,
+            self.passive_threat,
+        ]
+    }
+}
+
+fn count(bits: u64) -> i32 {
+    bits.count_ones() as i32
+}
+
+/// Counts evaluation terms for side
+pub fn terms(ctx: &Instance, side: Side) -> Terms {
+    let mut board = ctx.board;
+    let own = board.side_bitboard(side).num.data;
+    let theirs = board.side_bitboard(!side).num.data;
+    let passives = ctx.passive_tiles().num.data;
+    let aggressives = ctx.aggressive_tiles().num.data;
+    let own_edges = ctx.active_edges(side).num.data;
+    let their_edges = ctx.active_edges(!side).num.data;
+
+    let mut t = Terms {
+        passives: count(own & passives),
+        aggressives: count(own & aggressives),
+        ..Terms::default()
+    };
+
+    for file in [GOAT_E_NUMBER, SLOTH_E_NUMBER] {
+        let lined_up = count(own_edges & file & passives);
+        if their_edges & file == 0 {
+            t.edge_files += lined_up * lined_up;
+        }
+        if own_edges & file != 0 {
+            t.blockers += 1 + count(their_edges & file & passives);
+        }
+    }
+
+    for i in (own & passives).into_bitarray::<Msb0>().iter_ones() {
+        let (x, _) = Board::normal_to_pos(i as u64);
+        let to_edge = (x - 1).min(8 - x) as i32;
+        if to_edge > 0 {
+            t.edge_approach += (4 - to_edge).max(0);
+        }
+    }
+
+    let their_passives = theirs & passives;
+    let mut attacked = 0u64;
+    for k in 0..8 {
+        let p = Piece::from_index(k, side);
+        if !ctx.has_piece(p) {
+            continue;
+        }
+        if let Ok(mask) = board.move_mask_raw(p) {
+            t.mobility += count(mask.num.data & !theirs);
+            attacked |= mask.num.data & their_passives;
+        }
+    }
+    t.passive_threat = count(attacked);
+    if count(their_passives) == 1 {
+        t.passive_threat += 2;
+    }
+
+    t
+}
+
+/// Returns term counts as side minus opponent
+pub fn term_diff(ctx: &Instance, side: Side) -> [i32; 7] {
+    let own = terms(ctx, side).values();
+    let theirs = terms(ctx, !side).values();
+    let mut out = [0; 7];
+    for i in 0..7 {
+        out[i] = own[i] - theirs[i];
+    }
+    out
+}
+
+/// Returns weighted terms as side to move minus opponent, by name
+pub fn breakdown(ctx: &Instance, w: &Weights) -> Vec<(&'static str, i32)> {
+    let diff = term_diff(ctx, ctx.side);
+    NAMES
+        .iter()
+        .zip(w.values().iter().zip(diff))
+        .map(|(name, (weight, d))| (*name, (weight * d as f64).round() as i32))
+        .collect()
+}
+
+/// Scores position for the side to move
+pub fn evaluate(ctx: &Instance, w: &Weights) -> i32 {
+    let diff = term_diff(ctx, ctx.side);
+    w.values()
+        .iter()
+        .zip(diff)
+        .map(|(weight, d)| weight * d as f64)
+        .sum::<f64>()
+        .round() as i32
+}
@@ -1,46 +1,147 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, fmt, sync::Arc};
 
 use bitvec::{prelude::Msb0, view::BitViewSized};
 
 use crate::{
     bitboard::BitBoard,
     board::Board,
+    clock::Clocks,
     piece::{Piece, Side},
-    position::Position,
+    position::{Normalizable, Position},
 };
 
 #[derive(Clone, Debug)]
 pub struct Instance {
     pub board: Board,
     pub side: Side,
-    pub states: Arc<String>,
+    /// Move log in state file format. Owned by the instance; clones get their own copy.
+    pub states: String,
     pub call: HashMap<Side, bool>,
     pub miss_call: HashMap<Side, bool>,
     pub last_move: HashMap<Side, Piece>,
+    pub outcome: Option<Outcome>,
+    /// `None` for untimed games
+    pub clocks: Option<Clocks>,
+    pub observers: Observers,
 }
 
-impl std::ops::Not for Side {
-    type Output = Self;
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum WinReason {
+    EdgeAlignment,
+    PassiveElimination,
+    /// opponent ran out of time
+    Timeout,
+}
 
-    fn not(self) -> Self::Output {
-        match self {
-            Side::Orange => Side::White,
-            Side::White => Side::Orange,
-        }
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Outcome {
+    Win(Side, WinReason),
+    Stalemate,
+}
+
+/// Receives game events from an [`Instance`] as they happen.
+/// Every hook is called after the instance has been updated.
+pub trait GameObserver: Send + Sync {
+    /// piece moved between two positions, including miss-call penalty moves
+    fn on_move(&self, _ctx: &Instance, _p: Piece, _from: Position, _to: Position) {}
+    /// piece was taken off the board by a move. A miss-call penalty takes whatever stands
+    /// on the home tile, so the captured piece may be the mover's own.
+    fn on_capture(&self, _ctx: &Instance, _by: Piece, _captured: Piece, _at: Position) {}
+    fn on_call(&self, _ctx: &Instance, _side: Side) {}
+    /// side made a winning move without calling and will be penalized on its next turn
+    fn on_miss_call(&self, _ctx: &Instance, _side: Side) {}
+    fn on_game_over(&self, _ctx: &Instance, _outcome: Outcome) {}
+}
+
+/// Observers registered on an instance
+#[derive(Clone, Default)]
+pub struct Observers(pub Vec<Arc<dyn GameObserver>>);
+
+impl fmt::Debug for Observers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Observers({})", self.0.len())
     }
 }
+
 const WHITE_TEMPLATE_NUMBER: u64 =
     0b00000000_10000001_10000001_10000001_10000001_10000001_10000001_10000001;
 const ORANGE_TEMPLATE_NUMBER: u64 =
     0b10000001_10000001_10000001_10000001_10000001_10000001_10000001_00000000;
 
-const GOAT_E_NUMBER: u64 =
+pub const GOAT_E_NUMBER: u64 =
     0b10000000_10000000_10000000_10000000_10000000_10000000_10000000_10000000;
 
-const SLOTH_E_NUMBER: u64 =
+pub const SLOTH_E_NUMBER: u64 =
     0b00000001_00000001_00000001_00000001_00000001_00000001_00000001_00000001;
 
 impl Instance {
+    /// Returns instance with a blank board and no calls made, white to move
+    pub fn blank() -> Self {
+        Instance {
+            board: Board::blank(),
+            side: Side::White,
+            call: HashMap::from([(Side::White, false), (Side::Orange, false)]),
+            miss_call: HashMap::from([(Side::White, false), (Side::Orange, false)]),
+            last_move: HashMap::from([(Side::White, Piece::None), (Side::Orange, Piece::None)]),
+            states: String::from(""),
+            outcome: None,
+            clocks: None,
+            observers: Observers::default(),
+        }
+    }
+
+    /// Returns instance with the starting board and no calls made, white to move
+    pub fn start() -> Self {
+        let mut ctx = Instance::blank();
+        ctx.board = Board::new();
+        ctx
+    }
+
+    /// Registers observer to be notified of game events
+    pub fn observe(&mut self, o: Arc<dyn GameObserver>) {
+        self.observers.0.push(o);
+    }
+
+    /// Appends current board and side to the move log as a new state header
+    pub fn log_board(&mut self) {
+        let header = format!("{} {}\n", self.board.encode(), self.side);
+        self.states.push_str(header.as_str());
+    }
+
+    /// Appends a move to the move log
+    pub fn log_move(&mut self, p: Piece, pos: Position) {
+        if let Ok(enc) = pos.encode() {
+            let entry = format!("{} {},", p.encode(), enc);
+            self.states.push_str(entry.as_str());
+        }
+    }
+
+    /// Appends a win call to the move log
+    pub fn log_call(&mut self) {
+        self.states.push_str("c,");
+    }
+
+    fn notify(&self, f: impl Fn(&dyn GameObserver)) {
+        for o in self.observers.0.iter() {
+            f(o.as_ref());
+        }
+    }
+
+    /// Returns piece at position or `Piece::None` if empty or off the board
+    pub fn piece_at(&self, pos: Position) -> Piece {
+        if !pos.is_valid() || pos.0 > 8 || pos.1 > 8 {
+            return Piece::None;
+        }
+        self.board.piece_from_norm(pos.normal() as u64)
+    }
+
+    fn notify_move(&self, p: Piece, from: Position, to: Position, captured: Piece) {
+        if captured != Piece::None {
+            self.notify(|o| o.on_capture(self, p, captured, to));
+        }
+        self.notify(|o| o.on_move(self, p, from, to));
+    }
+
     pub fn game_set(&self) -> bool {
         if self.board.board_state().data == 0 {
             return false;
@@ -60,11 +161,45 @@ impl Instance {
         }
     }
 
-    pub fn make_move(&mut self, p: Piece, pos: Position) {
+    /// Ends the game as a loss on time if the side to move's clock has run out. Returns
+    /// true if it has.
+    pub fn flag_fall(&mut self) -> bool {
+        let flagged = self.clocks.is_some_and(|c| c.flagged(self.side));
+        if !flagged || self.outcome.is_some() {
+            return false;
+        }
+        let outcome = Outcome::Win(!self.side, WinReason::Timeout);
+        self.outcome = Some(outcome);
+        if let Some(clocks) = self.clocks.as_mut() {
+            clocks.stop(self.side);
+        }
+        self.notify(|o| o.on_game_over(self, outcome));
+        true
+    }
+
+    /// Charges the turn just played to mover's clock
+    fn end_turn_clock(&mut self, mover: Side) {
+        let over = self.outcome.is_some();
+        if let Some(clocks) = self.clocks.as_mut() {
+            if over {
+                clocks.stop(mover);
+            } else {
+                clocks.end_turn(mover);
+            }
+        }
+    }
+
+    /// Plays a move for the side to move. Returns false if the move was rejected.
+    pub fn make_move(&mut self, p: Piece, pos: Position) -> bool {
         let (_, side) = Piece::decode(p.encode()).unwrap();
         if side != self.side {
             println!("{}'s turn", self.side);
-            return;
+            return false;
+        }
+        self.flag_fall();
+        if self.outcome.is_some() {
+            println!("Game over");
+            return false;
         }
         if *self.miss_call.get(&self.side).unwrap() == true {
             let last_piece = *self.last_move.get(&self.side).unwrap();
@@ -72,20 +207,49 @@ impl Instance {
                 *self.miss_call.get_mut(&self.side).unwrap() = false;
                 self.side = !self.side;
             } else {
+                let from = self.board.pos_from_piece(last_piece).unwrap();
+                if pos == from {
+                    // the penalty has to move the piece
+                    return false;
+                }
+                let captured = self.piece_at(pos);
                 if self.board.unsafe_miss_call_position(last_piece, pos) == true {
                     *self.miss_call.get_mut(&self.side).unwrap() = false;
+                    self.end_turn_clock(self.side);
                     self.side = !self.side;
-                    return;
+                    self.notify_move(last_piece, from, pos, captured);
+                    return true;
                 } else {
-                    return;
+                    return false;
                 }
             }
         }
+        let from = self.board.pos_from_piece(p);
+        let captured = self.piece_at(pos);
         if self.board.new_position(p, pos) == true {
-            self.has_win();
+            let mover = self.side;
+            let missed = *self.miss_call.get(&mover).unwrap();
+            let reason = self.win_reason();
+            let won = self.has_win();
             *self.last_move.get_mut(&self.side).unwrap() = p;
             self.side = !self.side;
+
+            self.notify_move(p, from.unwrap(), pos, captured);
+            if !missed && *self.miss_call.get(&mover).unwrap() {
+                self.notify(|o| o.on_miss_call(self, mover));
+            }
+            if won {
+                self.outcome = reason.map(|r| Outcome::Win(mover, r));
+            } else if self.has_stalemate() {
+                self.outcome = Some(Outcome::Stalemate);
+            }
+            self.end_turn_clock(mover);
+            if let Some(outcome) = self.outcome {
+                self.notify(|o| o.on_game_over(self, outcome));
+            }
+            return true;
         }
+        false
     }
 
     pub fn passive_tiles(&self) -> BitBoard {
@@ -159,14 +323,22 @@ impl Instance {
         return (has_g_side, has_s_side);
     }
 
-    pub fn has_win(&mut self) -> bool {
+    /// Returns why the side to move could win right now, ignoring whether it called
+    pub fn win_reason(&self) -> Option<WinReason> {
         let align = self.has_alignment();
         let passives = self.has_passiveless();
 
-        let mut winable = false;
-        if align.0 == true || align.1 == true || *passives.get(&(!self.side)).unwrap() == true {
-            winable = true;
+        if align.0 || align.1 {
+            return Some(WinReason::EdgeAlignment);
+        }
+        if passives[&!self.side] {
+            return Some(WinReason::PassiveElimination);
         }
+        None
+    }
+
+    pub fn has_win(&mut self) -> bool {
+        let winable = self.win_reason().is_some();
 
         if self.call.get(&self.side).unwrap().to_owned() == false && winable == true {
             self.miss_call.insert(self.side, true);
@@ -181,8 +353,14 @@ impl Instance {
     }
 
     pub fn call_win(&mut self) {
-        *self.call.get_mut(&self.side).unwrap() = true;
+        if self.flag_fall() {
+            return;
+        }
+        let side = self.side;
+        *self.call.get_mut(&side).unwrap() = true;
+        self.end_turn_clock(side);
         self.side = !self.side;
+        self.notify(|o| o.on_call(self, side));
     }
 
     pub fn has_stalemate(&self) -> bool {
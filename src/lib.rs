@@ -1,6 +1,64 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+/// Prints rule diagnostics to stdout when std is available
+macro_rules! report {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "std")]
+        std::println!($($arg)*);
+    };
+}
+
+#[cfg(feature = "std")]
+pub mod annotate;
+#[cfg(feature = "std")]
+pub mod arena;
+#[cfg(feature = "std")]
+pub mod batch;
 pub mod bitboard;
 pub mod board;
+#[cfg(feature = "std")]
+pub mod book;
+#[cfg(feature = "std")]
+pub mod clock;
+#[cfg(feature = "std")]
+pub mod dataset;
+#[cfg(feature = "std")]
+pub mod eval;
+#[cfg(feature = "std")]
 pub mod game;
+pub mod mailbox;
+#[cfg(feature = "std")]
+pub mod mcts;
+#[cfg(feature = "std")]
+pub mod movegen;
+#[cfg(feature = "nnue")]
+pub mod nnue;
+#[cfg(feature = "std")]
+pub mod pack;
+#[cfg(feature = "std")]
+pub mod perft;
 pub mod piece;
+#[cfg(feature = "std")]
+pub mod player;
 pub mod position;
+#[cfg(feature = "std")]
+pub mod puzzle;
+#[cfg(feature = "std")]
+pub mod record;
+#[cfg(feature = "cli")]
 pub mod repl;
+pub mod rng;
+#[cfg(feature = "std")]
+pub mod search;
+#[cfg(feature = "std")]
+pub mod selfplay;
+#[cfg(feature = "std")]
+pub mod shared;
+#[cfg(feature = "std")]
+pub mod solve;
+#[cfg(feature = "std")]
+pub mod tablebase;
+#[cfg(feature = "std")]
+pub mod tune;
@@ -0,0 +1,192 @@
+/*!
+Reference move generator working on a plain 64 tile array.
+
+Written straight from the movement rules rather than from `Board::move_mask_raw` so the two
+can be compared against each other:
+
+- goat and horse step one tile in any of the eight directions onto an empty tile
+- sloth steps one tile orthogonally onto an empty tile
+- bird steps like a goat
+- tiger, otter, snake and mantis shrimp step one tile diagonally onto an empty tile
+- bird and the aggressive pieces also take an opponent piece straight ahead or ahead and one
+  column down. Ahead is towards row h for white and towards row a for orange.
+
+Nothing wraps around the board edges and no piece takes its own side.
+*/
+
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::{
+    bitboard::BitBoard,
+    board::Board,
+    piece::{Piece, Side},
+    position::{Normalizable, Position},
+    rng::Rng,
+};
+
+const KING_STEPS: [(i64, i64); 8] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-1, 0),
+    (1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+];
+const ORTHOGONAL_STEPS: [(i64, i64); 4] = [(0, -1), (-1, 0), (1, 0), (0, 1)];
+const DIAGONAL_STEPS: [(i64, i64); 4] = [(-1, -1), (1, -1), (-1, 1), (1, 1)];
+
+pub struct Mailbox {
+    tiles: [Piece; 64],
+}
+
+impl Mailbox {
+    pub fn from_board(b: &Board) -> Self {
+        let mut tiles = [Piece::None; 64];
+        for (i, t) in tiles.iter_mut().enumerate() {
+            *t = b.piece_from_norm(i as u64);
+        }
+        Mailbox { tiles }
+    }
+
+    /// Returns piece at column x and row y or `None` when off the board
+    fn at(&self, x: i64, y: i64) -> Option<Piece> {
+        if !(1..=8).contains(&x) || !(1..=8).contains(&y) {
+            return None;
+        }
+        Some(self.tiles[((y - 1) * 8 + (x - 1)) as usize])
+    }
+
+    /// Finds first tile holding piece
+    pub fn find(&self, p: Piece) -> Option<Position> {
+        self.tiles
+            .iter()
+            .position(|t| *t == p)
+            .map(|i| Board::normal_to_pos(i as u64))
+    }
+
+    /// Generates bitboard of possible moves for piece
+    pub fn move_mask(&self, p: Piece) -> Result<BitBoard, &'static str> {
+        let side = p.side().ok_or("Invalid piece")?;
+        let (x, y) = match self.find(p) {
+            Some((x, y)) => (x as i64, y as i64),
+            None => return Err("piece not on board"),
+        };
+        let mut out = BitBoard::new();
+        let mut mark = |tx: i64, ty: i64| {
+            out.num.set((tx as u64, ty as u64).normal(), true);
+        };
+
+        let steps: &[(i64, i64)] = match p {
+            Piece::None => &[],
+            Piece::Goat(_) | Piece::Horse(_) | Piece::Bird(_) => &KING_STEPS,
+            Piece::Sloth(_) => &ORTHOGONAL_STEPS,
+            Piece::Tiger(_) | Piece::Otter(_) | Piece::Snake(_) | Piece::MantisShrimp(_) => {
+                &DIAGONAL_STEPS
+            }
+        };
+        for (dx, dy) in steps {
+            if self.at(x + dx, y + dy) == Some(Piece::None) {
+                mark(x + dx, y + dy);
+            }
+        }
+
+        let takes = !matches!(p, Piece::Goat(_) | Piece::Horse(_) | Piece::Sloth(_));
+        if takes {
+            let ahead = match side {
+                Side::White => 1,
+                Side::Orange => -1,
+            };
+            for dx in [0, -1] {
+                if let Some(target) = self.at(x + dx, y + ahead) {
+                    if target.side() == Some(!side) {
+                        mark(x + dx, y + ahead);
+                    }
+                }
+            }
+        }
+
+        Ok(out)
+    }
+}
+
+/// Piece whose bitboard moves differ from the reference moves
+#[derive(Clone, Debug)]
+pub struct Mismatch {
+    pub piece: Piece,
+    pub from: Position,
+    /// moves only the bitboard generator produces
+    pub extra: BitBoard,
+    /// moves only the reference generator produces
+    pub missing: BitBoard,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}{}",
+            self.piece.encode(),
+            self.from.encode().unwrap_or_default()
+        )?;
+        for (label, bits) in [("extra", self.extra), ("missing", self.missing)] {
+            if bits.num.not_any() {
+                continue;
+            }
+            write!(f, " {}:", label)?;
+            for i in bits.num.iter_ones() {
+                let to = Board::normal_to_pos(i as u64);
+                write!(f, " {}", to.encode().unwrap_or_default())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Compares `Board::move_mask_raw` with the reference generator for every piece on board
+pub fn diff(board: &Board) -> Vec<Mismatch> {
+    let mailbox = Mailbox::from_board(board);
+    let mut out = Vec::new();
+    for side in [Side::White, Side::Orange] {
+        for k in 0..8 {
+            let p = Piece::from_index(k, side);
+            let from = match mailbox.find(p) {
+                Some(pos) => pos,
+                None => continue,
+            };
+            let reference = mailbox.move_mask(p);
+            let raw = board.clone().move_mask_raw(p);
+            let (reference, raw) = match (reference, raw) {
+                (Ok(a), Ok(b)) => (a.num.data, b.num.data),
+                _ => continue,
+            };
+            if reference != raw {
+                out.push(Mismatch {
+                    piece: p,
+                    from,
+                    extra: BitBoard::from_bitarray((raw & !reference).into()),
+                    missing: BitBoard::from_bitarray((reference & !raw).into()),
+                });
+            }
+        }
+    }
+    out
+}
+
+/// Returns board with a random subset of both sides' pieces on random tiles
+pub fn random_board(rng: &mut Rng) -> Board {
+    let mut b = Board::blank();
+    let mut free: Vec<usize> = (0..64).collect();
+    for side in [Side::White, Side::Orange] {
+        for k in 0..8 {
+            if rng.chance(0.25) {
+                continue;
+            }
+            let tile = free.swap_remove(rng.below(free.len()));
+            let _ = b.put(Piece::from_index(k, side), tile);
+        }
+    }
+    b
+}
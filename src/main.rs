@@ -1,38 +1,904 @@
 #![feature(panic_info_message)]
-pub mod bitboard;
-pub mod board;
-pub mod game;
-pub mod piece;
-pub mod position;
-mod repl;
-
 use std::{
+    collections::HashMap,
     env,
-    io::{self, BufRead},
+    fs::{self, File},
+    io::{self, BufRead, BufWriter},
     panic::set_hook,
+    path::PathBuf,
+    process,
+    sync::Arc,
     thread,
+    time::{Duration, Instant},
 };
 
-use clap::arg;
+use clap::{arg, value_parser, ArgMatches};
+use rayon::prelude::*;
 
-use crate::{
-    game::Instance,
-    repl::{blank_instance, run},
+use gtc::{
+    annotate::{self, Mark},
+    arena::{self, MatchConfig, Sprt, SprtResult, Wdl},
+    batch,
+    board::Board,
+    book::Book,
+    dataset::{self, RecordWriter, TrainingRecord},
+    eval::Weights,
+    game::{Instance, Outcome},
+    mailbox,
+    piece::Side,
+    player::{self, GameResult, PlayerConfig},
+    puzzle::{self, Theme},
+    record::GameRecord,
+    repl::{self, blank_instance, perft_cmd, run, Computer, Settings},
+    rng::Rng,
+    selfplay::{self, SelfPlayConfig},
+    tablebase::{self, Material},
+    tune,
 };
 
-fn body(ctx: &mut Instance, new: bool) {
-    let _args: Vec<String> = env::args().collect();
-
-    let mut cmd = clap::Command::new("gtc")
+fn cli() -> clap::Command {
+    clap::Command::new("gtc")
         .bin_name("gtc")
         .version(env!("CARGO_PKG_VERSION"))
         .author("Ashtyn MB")
-        .args([arg!(--mode <MODE> "sets mode of engine")
-            .value_parser(["protocol", "engine"])
-            .required(true)]);
+        .args([
+            arg!(--mode <MODE> "sets mode of engine")
+                .value_parser(["protocol", "engine"])
+                .required(true),
+            arg!(--skill <LEVEL> "play against the computer at this skill (0-20)")
+                .value_parser(value_parser!(u8).range(0..=20)),
+            arg!(--computer <SIDE> "side the computer plays")
+                .value_parser(["White", "Orange"])
+                .default_value("Orange"),
+        ])
+        .subcommand_negates_reqs(true)
+        .subcommand(
+            clap::Command::new("verify")
+                .about("compares bitboard move generation against the reference generator")
+                .args([
+                    arg!(--random <N> "number of random positions to check")
+                        .value_parser(value_parser!(usize))
+                        .default_value("10000"),
+                    arg!(--seed <SEED> "seed for random positions").value_parser(value_parser!(u64)),
+                    arg!([FILES] ... "state files to check, defaults to test_data")
+                        .value_parser(value_parser!(PathBuf)),
+                ]),
+        )
+        .subcommand(
+            clap::Command::new("perft")
+                .about("counts leaf nodes of the move tree")
+                .args([
+                    arg!(<DEPTH> "number of turns to search").value_parser(value_parser!(u32)),
+                    arg!(--divide "break the count down per root move"),
+                    arg!(--board <TILES> "board in tile notation, defaults to the start position"),
+                    arg!(--side <SIDE> "side to move")
+                        .value_parser(["White", "Orange"])
+                        .default_value("White"),
+                ]),
+        )
+        .subcommand(
+            clap::Command::new("batch")
+                .about("runs rule checks over a file of positions in parallel")
+                .args([arg!(<FILE> "one position per line: <tiles> [side]")
+                    .value_parser(value_parser!(PathBuf))]),
+        )
+        .subcommand(
+            clap::Command::new("tune")
+                .about("fits evaluation weights to the results of saved games")
+                .args([
+                    arg!(<FILES> ... "saved games or directories of them")
+                        .value_parser(value_parser!(PathBuf)),
+                    arg!(--out <FILE> "where to write the fitted weights").required(true),
+                    arg!(--weights <FILE> "weights to start from, defaults to the built-in ones"),
+                    arg!(--passes <N> "maximum number of passes over the weights")
+                        .value_parser(value_parser!(usize))
+                        .default_value("200"),
+                ]),
+        )
+        .subcommand(
+            clap::Command::new("export")
+                .about("writes positions of saved games with search scores as training data")
+                .args([
+                    arg!(<FILES> ... "saved games or directories of them")
+                        .value_parser(value_parser!(PathBuf)),
+                    arg!(--out <FILE> "where to write the training records").required(true),
+                    arg!(--depth <N> "search depth used to score each position")
+                        .value_parser(value_parser!(u32))
+                        .default_value("4"),
+                ]),
+        )
+        .subcommand(
+            clap::Command::new("duel")
+                .about("plays two computer players against each other from the start position")
+                .args([
+                    arg!(<A> "player spec: random, greedy, lookahead[:depth] or mcts[:iterations]"),
+                    arg!(<B> "player spec for the other side"),
+                    arg!(--games <N> "number of games, sides alternate with A white first")
+                        .value_parser(value_parser!(usize))
+                        .default_value("10"),
+                    arg!(--"max-plies" <N> "turns before a game is given up as unfinished")
+                        .value_parser(value_parser!(usize))
+                        .default_value("300"),
+                    arg!(--seed <SEED> "seed for the players' random choices")
+                        .value_parser(value_parser!(u64)),
+                    arg!(--book <FILE> "opening book for players with the book option"),
+                ]),
+        )
+        .subcommand(
+            clap::Command::new("selfplay")
+                .about("plays engine games and writes their positions as training data")
+                .args([
+                    arg!(--games <N> "number of games to play")
+                        .value_parser(value_parser!(usize))
+                        .default_value("100"),
+                    arg!(--depth <N> "search depth of both players")
+                        .value_parser(value_parser!(u32))
+                        .default_value("2"),
+                    arg!(--out <FILE> "where to write the training records").required(true),
+                    arg!(--"random-plies" <N> "most random turns at the start of a game")
+                        .value_parser(value_parser!(usize))
+                        .default_value("8"),
+                    arg!(--"max-plies" <N> "turns before a game is given up as unfinished")
+                        .value_parser(value_parser!(usize))
+                        .default_value("300"),
+                    arg!(--repetitions <N> "times a position may come up before a game is given up")
+                        .value_parser(value_parser!(usize))
+                        .default_value("3"),
+                    arg!(--seed <SEED> "seed for the random openings")
+                        .value_parser(value_parser!(u64)),
+                    arg!(--"save-games" <DIR> "also save every game as a state file in DIR")
+                        .value_parser(value_parser!(PathBuf)),
+                ]),
+        )
+        .subcommand(
+            clap::Command::new("match")
+                .about("plays two engines against each other over the protocol")
+                .args([
+                    arg!(--"engine-a" <CMD> "command starting the first engine in protocol mode")
+                        .required(true),
+                    arg!(--"engine-b" <CMD> "command starting the second engine").required(true),
+                    arg!(--games <N> "most games to play, sides alternate with A white first")
+                        .value_parser(value_parser!(usize))
+                        .default_value("10"),
+                    arg!(--depth <N> "search depth sent with go").value_parser(value_parser!(u32)),
+                    arg!(--movetime <MS> "move time sent with go")
+                        .value_parser(value_parser!(u64)),
+                    arg!(--"opening-plies" <N> "random turns at the start of each pair of games")
+                        .value_parser(value_parser!(usize))
+                        .default_value("4"),
+                    arg!(--"max-plies" <N> "turns before a game is drawn")
+                        .value_parser(value_parser!(usize))
+                        .default_value("300"),
+                    arg!(--seed <SEED> "seed for the random openings")
+                        .value_parser(value_parser!(u64)),
+                    arg!(--elo0 <ELO> "SPRT null hypothesis")
+                        .value_parser(value_parser!(f64))
+                        .default_value("0"),
+                    arg!(--elo1 <ELO> "SPRT alternative hypothesis")
+                        .value_parser(value_parser!(f64))
+                        .default_value("10"),
+                    arg!(--alpha <P> "SPRT false positive rate")
+                        .value_parser(value_parser!(f64))
+                        .default_value("0.05"),
+                    arg!(--beta <P> "SPRT false negative rate")
+                        .value_parser(value_parser!(f64))
+                        .default_value("0.05"),
+                ]),
+        )
+        .subcommand(
+            clap::Command::new("book")
+                .about("works with opening books")
+                .subcommand_required(true)
+                .subcommand(
+                    clap::Command::new("build")
+                        .about("builds an opening book from saved games and engine self-play")
+                        .args([
+                            arg!([FILES] ... "saved games or directories of them")
+                                .value_parser(value_parser!(PathBuf)),
+                            arg!(--out <FILE> "where to write the book").required(true),
+                            arg!(--plies <N> "turns of each game added to the book")
+                                .value_parser(value_parser!(usize))
+                                .default_value("16"),
+                            arg!(--selfplay <N> "also play this many self-play games")
+                                .value_parser(value_parser!(usize))
+                                .default_value("0"),
+                            arg!(--depth <N> "search depth of self-play games")
+                                .value_parser(value_parser!(u32))
+                                .default_value("2"),
+                            arg!(--seed <SEED> "seed for the self-play openings")
+                                .value_parser(value_parser!(u64)),
+                        ]),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("tablebase")
+                .about("generates endgame tables by retrograde analysis")
+                .args([
+                    arg!(<MATERIAL> "white pieces, v, orange pieces, e.g. givli"),
+                    arg!(--out <DIR> "directory for the table files, existing tables are reused")
+                        .value_parser(value_parser!(PathBuf))
+                        .default_value("tablebase"),
+                ]),
+        )
+        .subcommand(
+            clap::Command::new("annotate")
+                .about("reviews a saved game, marking blunders, mistakes and missed calls")
+                .args([
+                    arg!(<FILE> "saved game").value_parser(value_parser!(PathBuf)),
+                    arg!(--depth <N> "search depth used to score each position")
+                        .value_parser(value_parser!(u32))
+                        .default_value("3"),
+                ]),
+        )
+        .subcommand(
+            clap::Command::new("puzzles")
+                .about("finds puzzles in saved games and engine self-play")
+                .args([
+                    arg!([FILES] ... "saved games or directories of them")
+                        .value_parser(value_parser!(PathBuf)),
+                    arg!(--out <FILE> "where to write the puzzles").required(true),
+                    arg!(--"max-depth" <N> "longest forced line in turns")
+                        .value_parser(value_parser!(u32))
+                        .default_value("5"),
+                    arg!(--selfplay <N> "also play this many self-play games")
+                        .value_parser(value_parser!(usize))
+                        .default_value("0"),
+                    arg!(--depth <N> "search depth of self-play games")
+                        .value_parser(value_parser!(u32))
+                        .default_value("2"),
+                    arg!(--seed <SEED> "seed for the self-play openings")
+                        .value_parser(value_parser!(u64)),
+                ]),
+        )
+}
+
+/// Expands directories into the files directly inside them
+fn expand_files(paths: Vec<PathBuf>) -> Vec<PathBuf> {
+    let mut files = vec![];
+    for p in paths {
+        if p.is_dir() {
+            if let Ok(dir) = fs::read_dir(&p) {
+                let mut inner: Vec<PathBuf> = dir
+                    .filter_map(|e| e.ok().map(|e| e.path()))
+                    .filter(|p| p.is_file())
+                    .collect();
+                inner.sort();
+                files.extend(inner);
+            }
+        } else {
+            files.push(p);
+        }
+    }
+    files
+}
+
+/// Loads every saved game under paths, reporting the ones that can't be read
+fn load_records(paths: Vec<PathBuf>) -> Vec<GameRecord> {
+    let mut records = vec![];
+    for f in expand_files(paths) {
+        match GameRecord::load(f.to_str().unwrap_or_default()) {
+            Ok(r) => records.push(r),
+            Err(e) => println!("{}: skipped, {}", f.display(), e),
+        }
+    }
+    records
+}
+
+/// Scores every position of saved games and writes them out as training records
+fn export(sub: &ArgMatches) {
+    let paths: Vec<PathBuf> = sub.get_many::<PathBuf>("FILES").unwrap().cloned().collect();
+    let records = load_records(paths);
+    let depth = *sub.get_one::<u32>("depth").unwrap();
+    let start = Instant::now();
+    let labelled = dataset::label_games(&records, depth);
+
+    let out = sub.get_one::<String>("out").unwrap();
+    if let Err(e) = write_records(out, labelled.iter()) {
+        println!("{}: {}", out, e);
+        process::exit(1);
+    }
+    println!(
+        "{} games, {} positions at depth {} ({} ms)",
+        records.len(),
+        labelled.len(),
+        depth,
+        start.elapsed().as_millis()
+    );
+    println!("saved: {}", out);
+}
+
+/// Plays two players against each other and prints the score
+fn duel(sub: &ArgMatches) {
+    let mut cfg = PlayerConfig::default();
+    if let Some(path) = sub.get_one::<String>("book") {
+        match Book::load(path) {
+            Ok(b) => cfg.book = Some(Arc::new(b)),
+            Err(e) => {
+                println!("{}: {}", path, e);
+                process::exit(1);
+            }
+        }
+    }
+    let specs = [
+        sub.get_one::<String>("A").unwrap(),
+        sub.get_one::<String>("B").unwrap(),
+    ];
+    for spec in specs {
+        if let Err(e) = player::from_spec(spec, 0, &cfg) {
+            println!("{}: {}", spec, e);
+            process::exit(1);
+        }
+    }
+    let games = *sub.get_one::<usize>("games").unwrap();
+    let max_plies = *sub.get_one::<usize>("max-plies").unwrap();
+    let seed = sub
+        .get_one::<u64>("seed")
+        .copied()
+        .unwrap_or_else(|| Rng::from_time().next_u64());
+    let start = Instance::start();
+
+    let results: Vec<(bool, GameResult)> = (0..games)
+        .into_par_iter()
+        .map(|i| {
+            let game_seed = seed ^ (i as u64 + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+            let mut a = player::from_spec(specs[0], game_seed, &cfg).unwrap();
+            let mut b = player::from_spec(specs[1], !game_seed, &cfg).unwrap();
+            let a_white = i % 2 == 0;
+            let result = if a_white {
+                player::play_game(&start, a.as_mut(), b.as_mut(), max_plies)
+            } else {
+                player::play_game(&start, b.as_mut(), a.as_mut(), max_plies)
+            };
+            (a_white, result)
+        })
+        .collect();
+
+    let (mut a_wins, mut b_wins, mut stalemates, mut unfinished) = (0, 0, 0, 0);
+    for (i, (a_white, result)) in results.iter().enumerate() {
+        let a_side = if *a_white { Side::White } else { Side::Orange };
+        let label = match result.outcome {
+            Some(Outcome::Win(side, reason)) => {
+                if side == a_side {
+                    a_wins += 1;
+                } else {
+                    b_wins += 1;
+                }
+                format!("{} wins by {:?}", side, reason)
+            }
+            Some(Outcome::Stalemate) => {
+                stalemates += 1;
+                "stalemate".to_string()
+            }
+            None => {
+                unfinished += 1;
+                "unfinished".to_string()
+            }
+        };
+        println!(
+            "game {}: {} (White) vs {} (Orange), {} after {} turns",
+            i + 1,
+            specs[!a_white as usize],
+            specs[*a_white as usize],
+            label,
+            result.record.moves.len()
+        );
+    }
+    println!(
+        "{} {} - {} {}, {} stalemates, {} unfinished (seed {})",
+        specs[0], a_wins, b_wins, specs[1], stalemates, unfinished, seed
+    );
+}
+
+/// Writes training records to path
+fn write_records<'a>(
+    path: &str,
+    records: impl Iterator<Item = &'a TrainingRecord>,
+) -> io::Result<()> {
+    let mut w = RecordWriter::new(BufWriter::new(File::create(path)?));
+    for r in records {
+        w.write(r)?;
+    }
+    w.flush()
+}
+
+/// Plays engines against each other until the game count or an SPRT bound is reached
+fn run_match(sub: &ArgMatches) {
+    let cfg = MatchConfig {
+        engine_a: sub.get_one::<String>("engine-a").unwrap().clone(),
+        engine_b: sub.get_one::<String>("engine-b").unwrap().clone(),
+        games: *sub.get_one::<usize>("games").unwrap(),
+        depth: sub.get_one::<u32>("depth").copied(),
+        movetime: sub
+            .get_one::<u64>("movetime")
+            .map(|ms| Duration::from_millis(*ms)),
+        opening_plies: *sub.get_one::<usize>("opening-plies").unwrap(),
+        max_plies: *sub.get_one::<usize>("max-plies").unwrap(),
+        seed: sub
+            .get_one::<u64>("seed")
+            .copied()
+            .unwrap_or_else(|| Rng::from_time().next_u64()),
+    };
+    let sprt = Sprt {
+        elo0: *sub.get_one::<f64>("elo0").unwrap(),
+        elo1: *sub.get_one::<f64>("elo1").unwrap(),
+        alpha: *sub.get_one::<f64>("alpha").unwrap(),
+        beta: *sub.get_one::<f64>("beta").unwrap(),
+    };
+
+    let mut wdl = Wdl::default();
+    let mut decision = SprtResult::Continue;
+    for i in 0..cfg.games {
+        let game = match arena::play_match_game(&cfg, i) {
+            Ok(g) => g,
+            Err(e) => {
+                println!("game {}: {}", i + 1, e);
+                process::exit(1);
+            }
+        };
+        wdl.add(game.score_a());
+        let (white, orange) = if game.a_white { ("A", "B") } else { ("B", "A") };
+        println!(
+            "game {}: {} (White) vs {} (Orange), {} after {} turns",
+            i + 1,
+            white,
+            orange,
+            game.ending,
+            game.record.moves.len()
+        );
+        decision = sprt.test(&wdl).1;
+        if decision != SprtResult::Continue {
+            break;
+        }
+    }
+
+    let (diff, margin) = wdl.elo();
+    let (llr, _) = sprt.test(&wdl);
+    let (lower, upper) = sprt.bounds();
+    println!(
+        "A vs B: {} wins, {} draws, {} losses",
+        wdl.wins, wdl.draws, wdl.losses
+    );
+    println!("Elo difference: {:.1} +/- {:.1}", diff, margin);
+    let verdict = match decision {
+        SprtResult::H1 => "H1 accepted",
+        SprtResult::H0 => "H0 accepted",
+        SprtResult::Continue => "inconclusive",
+    };
+    println!(
+        "SPRT elo0 {} elo1 {}: LLR {:.2} ({:.2}, {:.2}), {} (seed {})",
+        sprt.elo0, sprt.elo1, llr, lower, upper, verdict, cfg.seed
+    );
+}
+
+/// Prints a saved game with every move scored and the bad ones marked
+fn annotate_game(sub: &ArgMatches) {
+    let path = sub.get_one::<PathBuf>("FILE").unwrap();
+    let depth = *sub.get_one::<u32>("depth").unwrap();
+    let record = match GameRecord::load(path.to_str().unwrap_or_default()) {
+        Ok(r) => r,
+        Err(e) => {
+            println!("{}: {}", path.display(), e);
+            process::exit(1);
+        }
+    };
+    let start = Instant::now();
+    println!(
+        "From: {} {}",
+        record.start.board.encode(),
+        record.start.side
+    );
+    let notes = annotate::annotate(&record, depth);
+    for n in notes.iter() {
+        println!("{}", n);
+    }
+
+    for side in [Side::White, Side::Orange] {
+        let count = |mark: Mark| {
+            notes
+                .iter()
+                .filter(|n| n.side == side && n.mark == Some(mark))
+                .count()
+        };
+        let missed_calls = notes
+            .iter()
+            .filter(|n| n.side == side && n.missed_call)
+            .count();
+        println!(
+            "{}: {} blunders, {} mistakes, {} missed wins, {} missed calls",
+            side,
+            count(Mark::Blunder),
+            count(Mark::Mistake),
+            count(Mark::MissedWin),
+            missed_calls
+        );
+    }
+    println!(
+        "{} moves at depth {} ({} ms)",
+        notes.len(),
+        depth,
+        start.elapsed().as_millis()
+    );
+}
+
+/// Loads saved games given as FILES and plays the number of self-play games asked for.
+/// Self-play games keep their random opening turns only if openings is set.
+fn gather_games(sub: &ArgMatches, openings: bool) -> Vec<GameRecord> {
+    let paths: Vec<PathBuf> = sub
+        .get_many::<PathBuf>("FILES")
+        .map(|f| f.cloned().collect())
+        .unwrap_or_default();
+    let mut records = load_records(paths);
+    let games = *sub.get_one::<usize>("selfplay").unwrap();
+    if games > 0 {
+        let cfg = SelfPlayConfig {
+            depth: *sub.get_one::<u32>("depth").unwrap(),
+            seed: sub
+                .get_one::<u64>("seed")
+                .copied()
+                .unwrap_or_else(|| Rng::from_time().next_u64()),
+            ..SelfPlayConfig::default()
+        };
+        records.extend(selfplay::play_games(&cfg, games).into_iter().map(|g| {
+            if openings {
+                g.record
+            } else {
+                g.searched()
+            }
+        }));
+    }
+    records
+}
+
+/// Builds an opening book out of saved games and self-play games
+fn build_book(sub: &ArgMatches) {
+    let start = Instant::now();
+    // random opening turns would make their way into the book
+    let records = gather_games(sub, false);
+    if records.is_empty() {
+        println!("no games to build a book from");
+        process::exit(1);
+    }
+
+    let plies = *sub.get_one::<usize>("plies").unwrap();
+    let mut book = Book::new();
+    for r in records.iter() {
+        book.add_game(r, plies);
+    }
+    let out = sub.get_one::<String>("out").unwrap();
+    if let Err(e) = book.save(out) {
+        println!("{}: {}", out, e);
+        process::exit(1);
+    }
+    println!(
+        "{} games, {} positions ({} ms)",
+        records.len(),
+        book.len(),
+        start.elapsed().as_millis()
+    );
+    println!("saved: {}", out);
+}
+
+/// Finds puzzles in saved games and self-play games and writes them out
+fn find_puzzles(sub: &ArgMatches) {
+    let start = Instant::now();
+    let records = gather_games(sub, true);
+    if records.is_empty() {
+        println!("no games to look for puzzles in");
+        process::exit(1);
+    }
+
+    let max_depth = *sub.get_one::<u32>("max-depth").unwrap();
+    let puzzles = puzzle::mine(&records, max_depth);
+    let out = sub.get_one::<String>("out").unwrap();
+    if let Err(e) = puzzle::save(out, &puzzles) {
+        println!("{}: {}", out, e);
+        process::exit(1);
+    }
+    for theme in [
+        Theme::EdgeAlignment,
+        Theme::PassiveElimination,
+        Theme::MissCallTrap,
+    ] {
+        let count = puzzles.iter().filter(|p| p.theme == theme).count();
+        println!("{}: {}", theme, count);
+    }
+    println!(
+        "{} games, {} puzzles ({} ms)",
+        records.len(),
+        puzzles.len(),
+        start.elapsed().as_millis()
+    );
+    println!("saved: {}", out);
+}
+
+/// Generates the table of a material and every smaller one it needs
+fn generate_tables(sub: &ArgMatches) {
+    let material = match sub
+        .get_one::<String>("MATERIAL")
+        .unwrap()
+        .parse::<Material>()
+    {
+        Ok(m) => m,
+        Err(e) => {
+            println!("{}", e);
+            process::exit(1);
+        }
+    };
+    let dir = sub.get_one::<PathBuf>("out").unwrap();
+    if let Err(e) = fs::create_dir_all(dir) {
+        println!("{}: {}", dir.display(), e);
+        process::exit(1);
+    }
+    let start = Instant::now();
+    let mut tables = HashMap::new();
+    let mut report = |t: &tablebase::Table| {
+        let stats = t.stats();
+        println!(
+            "{}: {} wins, {} losses, {} draws, longest {} turns ({} ms)",
+            t.material,
+            stats.wins,
+            stats.losses,
+            stats.draws,
+            stats.longest,
+            start.elapsed().as_millis()
+        );
+    };
+    if let Err(e) = tablebase::generate_all(&material, dir, &mut tables, &mut report) {
+        println!("{}", e);
+        process::exit(1);
+    }
+    println!("saved: {}", tablebase::table_path(dir, &material).display());
+}
+
+/// Plays engine games and writes their positions out as training records
+fn selfplay(sub: &ArgMatches) {
+    let cfg = SelfPlayConfig {
+        depth: *sub.get_one::<u32>("depth").unwrap(),
+        random_plies: *sub.get_one::<usize>("random-plies").unwrap(),
+        max_plies: *sub.get_one::<usize>("max-plies").unwrap(),
+        repetitions: *sub.get_one::<usize>("repetitions").unwrap(),
+        seed: sub
+            .get_one::<u64>("seed")
+            .copied()
+            .unwrap_or_else(|| Rng::from_time().next_u64()),
+    };
+    let games = *sub.get_one::<usize>("games").unwrap();
+    let start = Instant::now();
+    let played = selfplay::play_games(&cfg, games);
+
+    let out = sub.get_one::<String>("out").unwrap();
+    if let Err(e) = write_records(out, played.iter().flat_map(|g| g.positions.iter())) {
+        println!("{}: {}", out, e);
+        process::exit(1);
+    }
+    if let Some(dir) = sub.get_one::<PathBuf>("save-games") {
+        let saved = fs::create_dir_all(dir)
+            .map_err(|_| "Couldn't create directory.")
+            .and_then(|_| {
+                played.iter().enumerate().try_for_each(|(i, g)| {
+                    let path = dir.join(format!("selfplay_{}_{}", cfg.seed, i));
+                    g.record.save(path.to_str().unwrap_or_default())
+                })
+            });
+        if let Err(e) = saved {
+            println!("{}: {}", dir.display(), e);
+            process::exit(1);
+        }
+    }
+
+    let mut counts: Vec<(String, usize)> = vec![];
+    for g in played.iter() {
+        let label = match g.outcome {
+            Some(Outcome::Win(side, reason)) => format!("{} by {:?}", side, reason),
+            Some(Outcome::Stalemate) => "stalemate".to_string(),
+            None => "unfinished".to_string(),
+        };
+        match counts.iter_mut().find(|(l, _)| *l == label) {
+            Some((_, n)) => *n += 1,
+            None => counts.push((label, 1)),
+        }
+    }
+    counts.sort();
+    for (label, n) in counts {
+        println!("{}: {}", label, n);
+    }
+    println!(
+        "{} games, {} positions, seed {} ({} ms)",
+        played.len(),
+        played.iter().map(|g| g.positions.len()).sum::<usize>(),
+        cfg.seed,
+        start.elapsed().as_millis()
+    );
+    println!("saved: {}", out);
+}
+
+/// Fits evaluation weights to saved games and writes them out
+fn tune(sub: &ArgMatches) {
+    let paths: Vec<PathBuf> = sub.get_many::<PathBuf>("FILES").unwrap().cloned().collect();
+    let records = load_records(paths);
+    let start = match sub.get_one::<String>("weights") {
+        Some(path) => match Weights::load(path) {
+            Ok(w) => w,
+            Err(e) => {
+                println!("{}", e);
+                process::exit(1);
+            }
+        },
+        None => Weights::default(),
+    };
+
+    let (samples, unfinished) = tune::samples(&records);
+    println!(
+        "{} games ({} unfinished skipped), {} positions",
+        records.len(),
+        unfinished,
+        samples.len()
+    );
+    if samples.is_empty() {
+        println!("no positions from finished games to tune on");
+        process::exit(1);
+    }
+
+    let k = tune::fit_k(&samples, &start);
+    println!(
+        "k: {:.3}, error: {:.6}",
+        k,
+        tune::error(&samples, &start, k)
+    );
+    let fitted = tune::tune(
+        &samples,
+        start,
+        k,
+        *sub.get_one::<usize>("passes").unwrap(),
+        |pass, e| println!("pass {}: error {:.6}", pass, e),
+    );
+    print!("{}", fitted);
+
+    let out = sub.get_one::<String>("out").unwrap();
+    if let Err(e) = fitted.save(out) {
+        println!("{}", e);
+        process::exit(1);
+    }
+    println!("saved: {}", out);
+}
+
+/// Prints rule check results for every position in a file
+fn batch(sub: &ArgMatches) {
+    let path = sub.get_one::<PathBuf>("FILE").unwrap();
+    let raw = match fs::read_to_string(path) {
+        Ok(raw) => raw,
+        Err(e) => {
+            println!("{}: {}", path.display(), e);
+            process::exit(1);
+        }
+    };
+    let lines: Vec<String> = raw.lines().map(|l| l.to_string()).collect();
+
+    let start = Instant::now();
+    let results = batch::check_all(&lines);
+    let mut failed = 0;
+    for (line, res) in results.iter() {
+        match res {
+            Ok(report) => println!("{}", report),
+            Err(e) => {
+                failed += 1;
+                println!("line {}: {}", line, e)
+            }
+        }
+    }
+    println!(
+        "checked {} positions ({} invalid) in {} ms",
+        results.len(),
+        failed,
+        start.elapsed().as_millis()
+    );
+}
+
+/// Runs perft from the start position or a given board
+fn perft(sub: &ArgMatches) {
+    let mut ctx = blank_instance();
+    ctx.board = match sub.get_one::<String>("board") {
+        Some(tiles) => match Board::decode(tiles.to_string()) {
+            Ok(b) => b,
+            Err(e) => {
+                println!("{}", e);
+                process::exit(1);
+            }
+        },
+        None => Board::new(),
+    };
+    ctx.side = sub
+        .get_one::<String>("side")
+        .unwrap()
+        .parse::<Side>()
+        .unwrap();
+    perft_cmd(
+        &ctx,
+        *sub.get_one::<u32>("DEPTH").unwrap(),
+        sub.get_flag("divide"),
+        false,
+    );
+}
+
+/// Checks fixture and random positions with the reference move generator
+fn verify(sub: &ArgMatches) {
+    let mut files: Vec<PathBuf> = sub
+        .get_many::<PathBuf>("FILES")
+        .map(|f| f.cloned().collect())
+        .unwrap_or_default();
+    if files.is_empty() {
+        if let Ok(dir) = fs::read_dir("test_data") {
+            files = dir.filter_map(|e| e.ok().map(|e| e.path())).collect();
+            files.sort();
+        }
+    }
+
+    let mut boards: Vec<(String, Board)> = vec![];
+    for f in files {
+        let raw = match fs::read_to_string(&f) {
+            Ok(raw) => raw,
+            Err(e) => {
+                println!("{}: {}", f.display(), e);
+                continue;
+            }
+        };
+        let head = raw.split_whitespace().next().unwrap_or_default();
+        match Board::decode(head.to_string()) {
+            Ok(b) => boards.push((f.display().to_string(), b)),
+            Err(e) => println!("{}: skipped, {}", f.display(), e),
+        }
+    }
+
+    let seed = sub
+        .get_one::<u64>("seed")
+        .copied()
+        .unwrap_or_else(|| Rng::from_time().next_u64());
+    let mut rng = Rng::new(seed);
+    for n in 0..*sub.get_one::<usize>("random").unwrap() {
+        boards.push((
+            format!("random #{} (seed {})", n, seed),
+            mailbox::random_board(&mut rng),
+        ));
+    }
+
+    let mut mismatched = 0;
+    for (name, b) in boards.iter() {
+        let found = mailbox::diff(b);
+        if found.is_empty() {
+            continue;
+        }
+        mismatched += 1;
+        println!("{}: {}", name, b.encode());
+        for m in found {
+            println!("  {}", m);
+        }
+    }
+    println!(
+        "checked {} positions, {} mismatched",
+        boards.len(),
+        mismatched
+    );
+    if mismatched > 0 {
+        process::exit(1);
+    }
+}
+
+fn body(ctx: &mut Instance, new: bool) {
+    let _args: Vec<String> = env::args().collect();
+
+    let mut cmd = cli();
 
     let matches = cmd.clone().get_matches();
     let mode = matches.get_one::<String>("mode").unwrap();
+    let mut computer = matches.get_one::<u8>("skill").map(|skill| {
+        let side = matches.get_one::<String>("computer").unwrap();
+        Computer {
+            side: side.parse::<Side>().unwrap(),
+            player: format!("skill:{}", skill),
+        }
+    });
+
+    let mut settings = Settings::default();
 
     match mode.to_string().as_str() {
         "protocol" => {
@@ -46,11 +912,11 @@ fn body(ctx: &mut Instance, new: bool) {
                     return;
                 }
                 println!();
-                repl::cmd(ctx, data.as_str(), true);
+                repl::cmd(ctx, &mut computer, &mut settings, data.as_str(), true);
             }
         }
         "engine" => {
-            run();
+            run(computer);
         }
         _ => cmd.print_help().expect("bad"),
     };
@@ -68,6 +934,25 @@ fn main() {
             println!("{}", info);
         }
     }));
+    match cli().get_matches().subcommand() {
+        Some(("verify", sub)) => return verify(sub),
+        Some(("perft", sub)) => return perft(sub),
+        Some(("batch", sub)) => return batch(sub),
+        Some(("tune", sub)) => return tune(sub),
+        Some(("export", sub)) => return export(sub),
+        Some(("selfplay", sub)) => return selfplay(sub),
+        Some(("duel", sub)) => return duel(sub),
+        Some(("tablebase", sub)) => return generate_tables(sub),
+        Some(("match", sub)) => return run_match(sub),
+        Some(("annotate", sub)) => return annotate_game(sub),
+        Some(("puzzles", sub)) => return find_puzzles(sub),
+        Some(("book", sub)) => match sub.subcommand() {
+            Some(("build", build)) => return build_book(build),
+            _ => return,
+        },
+        Some(_) => return,
+        None => {}
+    }
     thread::scope(|scope| loop {
         let mut inst: Instance = blank_instance();
         let status = scope.spawn(move || {
@@ -0,0 +1,260 @@
+/*!
+Monte Carlo tree search (UCT) with light-policy playouts.
+
+Each iteration walks down the tree picking the child with the best UCB1 score, expands one
+untried move, plays the game out to the end and backs the result up the path. Playouts are
+random except that captures of passives are preferred, since passive elimination is how
+most random games end. No evaluation is used: a node's value is the share of playouts
+through it won by the side that moved into it, with stalemates and cut-off playouts counted
+as half a win.
+*/
+
+use std::{
+    cmp::Reverse,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    game::{Instance, Outcome},
+    movegen::Move,
+    piece::Side,
+    rng::Rng,
+};
+
+/// Iterations used when neither iterations nor a move time is given
+pub const DEFAULT_ITERATIONS: u64 = 2000;
+/// Playouts still going after this many turns count as a draw
+pub const MAX_PLAYOUT: usize = 200;
+/// UCB1 exploration constant
+pub const EXPLORATION: f64 = 1.41;
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MctsLimits {
+    pub iterations: Option<u64>,
+    pub movetime: Option<Duration>,
+}
+
+/// Root move statistics
+#[derive(Clone, Copy, Debug)]
+pub struct MoveStats {
+    pub mv: Move,
+    pub visits: u32,
+    /// share of playouts won by the side to move at the root
+    pub win_rate: f64,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct MctsResult {
+    pub best: Option<Move>,
+    pub iterations: u64,
+    /// root moves, most visited first
+    pub moves: Vec<MoveStats>,
+    /// most visited line from the root
+    pub pv: Vec<Move>,
+}
+
+struct Node {
+    mv: Option<Move>,
+    /// side that played `mv`
+    mover: Side,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    untried: Vec<Move>,
+    visits: u32,
+    wins: f64,
+}
+
+impl Node {
+    fn new(mv: Option<Move>, mover: Side, parent: Option<usize>, ctx: &Instance) -> Self {
+        Node {
+            mv,
+            mover,
+            parent,
+            children: vec![],
+            untried: ctx.legal_moves(),
+            visits: 0,
+            wins: 0.0,
+        }
+    }
+}
+
+/// Returns result of a finished game for side: 1 win, 0.5 stalemate, 0 loss
+fn result_for(outcome: Option<Outcome>, side: Side) -> f64 {
+    match outcome {
+        Some(Outcome::Win(s, _)) if s == side => 1.0,
+        Some(Outcome::Win(_, _)) => 0.0,
+        _ => 0.5,
+    }
+}
+
+/// Picks a playout move: a capture of an opponent passive half the time if there is one,
+/// otherwise any legal move
+fn playout_move(ctx: &Instance, moves: &[Move], rng: &mut Rng) -> Move {
+    if rng.chance(0.5) {
+        let captures: Vec<Move> = moves
+            .iter()
+            .filter(|m| match m {
+                Move::Step(_, to) => {
+                    // passives come first in piece order
+                    let target = ctx.piece_at(*to);
+                    target.side() == Some(!ctx.side) && target.index().is_some_and(|k| k < 4)
+                }
+                Move::Call => false,
+            })
+            .copied()
+            .collect();
+        if !captures.is_empty() {
+            return captures[rng.below(captures.len())];
+        }
+    }
+    moves[rng.below(moves.len())]
+}
+
+/// Plays ctx out and returns its outcome, `None` if it was cut off
+pub fn playout(mut ctx: Instance, rng: &mut Rng) -> Option<Outcome> {
+    for _ in 0..MAX_PLAYOUT {
+        if ctx.outcome.is_some() {
+            break;
+        }
+        let mut moves = ctx.legal_moves();
+        loop {
+            if moves.is_empty() {
+                return ctx.outcome;
+            }
+            let m = playout_move(&ctx, &moves, rng);
+            if ctx.play(m) {
+                break;
+            }
+            moves.retain(|x| *x != m);
+        }
+    }
+    ctx.outcome
+}
+
+struct Tree {
+    nodes: Vec<Node>,
+}
+
+impl Tree {
+    fn ucb_child(&self, n: usize) -> usize {
+        let parent_visits = (self.nodes[n].visits.max(1) as f64).ln();
+        let mut best = (f64::MIN, self.nodes[n].children[0]);
+        for &c in self.nodes[n].children.iter() {
+            let child = &self.nodes[c];
+            let score = child.wins / child.visits as f64
+                + EXPLORATION * (parent_visits / child.visits as f64).sqrt();
+            if score > best.0 {
+                best = (score, c);
+            }
+        }
+        best.1
+    }
+
+    fn most_visited(&self, n: usize) -> Option<usize> {
+        self.nodes[n]
+            .children
+            .iter()
+            .copied()
+            .max_by_key(|c| self.nodes[*c].visits)
+    }
+
+    fn iterate(&mut self, root: &Instance, rng: &mut Rng) {
+        let mut ctx = root.scratch();
+        let mut n = 0;
+
+        // selection
+        while self.nodes[n].untried.is_empty() && !self.nodes[n].children.is_empty() {
+            n = self.ucb_child(n);
+            if let Some(m) = self.nodes[n].mv {
+                ctx.play(m);
+            }
+        }
+
+        // expansion
+        while !self.nodes[n].untried.is_empty() {
+            let i = rng.below(self.nodes[n].untried.len());
+            let m = self.nodes[n].untried.swap_remove(i);
+            let mover = ctx.side;
+            if !ctx.play(m) {
+                continue;
+            }
+            let child = Node::new(Some(m), mover, Some(n), &ctx);
+            self.nodes.push(child);
+            let c = self.nodes.len() - 1;
+            self.nodes[n].children.push(c);
+            n = c;
+            break;
+        }
+
+        let outcome = playout(ctx, rng);
+
+        // backpropagation
+        let mut at = Some(n);
+        while let Some(i) = at {
+            let node = &mut self.nodes[i];
+            node.visits += 1;
+            node.wins += result_for(outcome, node.mover);
+            at = node.parent;
+        }
+    }
+}
+
+/// Runs UCT search from ctx until limits are hit
+pub fn mcts(ctx: &Instance, limits: &MctsLimits, rng: &mut Rng) -> MctsResult {
+    let start = Instant::now();
+    let root = ctx.scratch();
+    let max_iterations = match (limits.iterations, limits.movetime) {
+        (Some(n), _) => n.max(1),
+        (None, Some(_)) => u64::MAX,
+        (None, None) => DEFAULT_ITERATIONS,
+    };
+    let deadline = limits.movetime.map(|t| start + t);
+
+    let mut tree = Tree {
+        nodes: vec![Node::new(None, !root.side, None, &root)],
+    };
+    let mut result = MctsResult::default();
+    if tree.nodes[0].untried.len() <= 1 {
+        result.best = tree.nodes[0].untried.first().copied();
+        result.pv = result.best.into_iter().collect();
+        return result;
+    }
+
+    while result.iterations < max_iterations {
+        if result.iterations & 63 == 0 {
+            if let Some(deadline) = deadline {
+                if Instant::now() >= deadline {
+                    break;
+                }
+            }
+        }
+        tree.iterate(&root, rng);
+        result.iterations += 1;
+    }
+
+    let mut moves: Vec<MoveStats> = tree.nodes[0]
+        .children
+        .iter()
+        .filter_map(|c| {
+            let node = &tree.nodes[*c];
+            Some(MoveStats {
+                mv: node.mv?,
+                visits: node.visits,
+                win_rate: node.wins / node.visits.max(1) as f64,
+            })
+        })
+        .collect();
+    moves.sort_by_key(|s| Reverse(s.visits));
+    result.best = moves.first().map(|s| s.mv);
+    result.moves = moves;
+
+    let mut at = 0;
+    while let Some(c) = tree.most_visited(at) {
+        match tree.nodes[c].mv {
+            Some(m) => result.pv.push(m),
+            None => break,
+        }
+        at = c;
+    }
+    result
+}
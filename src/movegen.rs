@@ -0,0 +1,253 @@
+/*!
+Legal move generation over a whole [`Instance`].
+
+A turn is either a step of one piece (`m` in the protocol) or a win call (`c`). While a
+side owes a miss-call penalty its only moves are putting the last moved piece back on any
+other home row tile, taking a piece already there. [`Instance::can_call`] tells whether a
+win is one step away, which is when a side should call. Moves use the same notation as the
+move log, e.g. `I b8` and `c`.
+
+[`Instance::unmoves`] goes the other way for retrograde analysis, listing the steps that
+could have led to a position.
+*/
+
+use std::fmt;
+
+use crate::{
+    board::Board,
+    game::{Instance, Outcome},
+    piece::{Piece, Side},
+    position::{decode_position, Normalizable, Position},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Move {
+    Step(Piece, Position),
+    Call,
+}
+
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Move::Step(p, pos) => write!(f, "{} {}", p.encode(), pos.encode().unwrap_or_default()),
+            Move::Call => write!(f, "c"),
+        }
+    }
+}
+
+impl Move {
+    /// Decodes move log notation (`I b8` or `c`)
+    pub fn decode(s: &str) -> Result<Move, &'static str> {
+        let parts = s.split_whitespace().collect::<Vec<&str>>();
+        match parts.as_slice() {
+            ["c"] | ["call"] => Ok(Move::Call),
+            [p, pos] => {
+                let (piece, _) = Piece::decode(p.to_string())?;
+                if piece == Piece::None {
+                    return Err("Invalid piece");
+                }
+                let pos = decode_position(pos.to_string())?;
+                if !pos.is_valid() || pos.0 > 8 || pos.1 > 8 {
+                    return Err("invalid position");
+                }
+                Ok(Move::Step(piece, pos))
+            }
+            _ => Err("invalid move string"),
+        }
+    }
+}
+
+/// A step taken back: the piece returns from `to` to `from`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Unmove {
+    pub piece: Piece,
+    pub from: Position,
+    pub to: Position,
+}
+
+/// Column and row offsets of the tiles a single step can cover
+const STEP_OFFSETS: [(i64, i64); 8] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-1, 0),
+    (1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+];
+
+/// Returns normalized positions of the home row of side
+pub fn home_row(s: Side) -> std::ops::Range<u64> {
+    match s {
+        Side::White => 0..8,
+        Side::Orange => 56..64,
+    }
+}
+
+impl Instance {
+    /// Returns copy of the game state without move log or observers, for lookahead
+    pub fn scratch(&self) -> Instance {
+        let mut ctx = Instance::blank();
+        ctx.board = self.board;
+        ctx.side = self.side;
+        ctx.call = self.call.clone();
+        ctx.miss_call = self.miss_call.clone();
+        ctx.last_move = self.last_move.clone();
+        ctx.outcome = self.outcome;
+        ctx
+    }
+
+    /// Returns true if piece is on the board
+    pub fn has_piece(&self, p: Piece) -> bool {
+        let side = match p.side() {
+            Some(s) => s,
+            None => return false,
+        };
+        let mut board = self.board;
+        let kind = match p {
+            Piece::None => return false,
+            Piece::Goat(_) => board.goats,
+            Piece::Horse(_) => board.horses,
+            Piece::Sloth(_) => board.sloths,
+            Piece::Bird(_) => board.birds,
+            Piece::Tiger(_) => board.tigers,
+            Piece::Otter(_) => board.otters,
+            Piece::Snake(_) => board.snakes,
+            Piece::MantisShrimp(_) => board.mantis_shrimps,
+        };
+        kind.num.data & board.side_bitboard(side).num.data != 0
+    }
+
+    /// Generates every move the side to move can play
+    pub fn legal_moves(&self) -> Vec<Move> {
+        let mut moves = vec![];
+        if self.outcome.is_some() {
+            return moves;
+        }
+
+        if self.miss_call[&self.side] {
+            let last = self.last_move[&self.side];
+            if last == Piece::None || !self.has_piece(last) {
+                return moves;
+            }
+            // the penalty may land on any other home tile, taking whatever stands there
+            let mut board = self.board;
+            let at = board.pos_from_piece(last).ok();
+            for i in home_row(self.side) {
+                let to = Board::normal_to_pos(i);
+                if Some(to) != at {
+                    moves.push(Move::Step(last, to));
+                }
+            }
+            return moves;
+        }
+
+        for k in 0..8 {
+            let p = Piece::from_index(k, self.side);
+            if !self.has_piece(p) {
+                continue;
+            }
+            let mut board = self.board;
+            if let Ok(mask) = board.move_mask_raw(p) {
+                for i in mask.num.iter_ones() {
+                    moves.push(Move::Step(p, Board::normal_to_pos(i as u64)));
+                }
+            }
+        }
+        if !self.call[&self.side] {
+            moves.push(Move::Call);
+        }
+        moves
+    }
+
+    /// Returns the steps that would win on the spot for the side to move once it has called
+    pub fn winning_steps(&self) -> Vec<Move> {
+        if self.outcome.is_some() || self.miss_call[&self.side] {
+            return vec![];
+        }
+        let mut called = self.scratch();
+        called.call.insert(self.side, true);
+        called
+            .legal_moves()
+            .into_iter()
+            .filter(|m| {
+                let mut trial = called.clone();
+                *m != Move::Call
+                    && trial.play(*m)
+                    && matches!(trial.outcome, Some(Outcome::Win(s, _)) if s == self.side)
+            })
+            .collect()
+    }
+
+    /// Returns true if the side to move hasn't called yet and a step would win. Stepping
+    /// there without calling first is a miss-call.
+    pub fn can_call(&self) -> bool {
+        !self.call[&self.side] && !self.winning_steps().is_empty()
+    }
+
+    /// Generates the steps without a capture the side that just moved could have played to
+    /// reach this position. Captures change the material and are left to the caller.
+    pub fn unmoves(&self) -> Vec<Unmove> {
+        let mover = !self.side;
+        let occupied = self.board.board_state();
+        let mut out = vec![];
+        for k in 0..8 {
+            let p = Piece::from_index(k, mover);
+            if !self.has_piece(p) {
+                continue;
+            }
+            let mut board = self.board;
+            let to = match board.pos_from_piece(p) {
+                Ok(pos) => pos,
+                Err(_) => continue,
+            };
+            let t = to.normal();
+            for (dx, dy) in STEP_OFFSETS {
+                let (x, y) = (to.0 as i64 - dx, to.1 as i64 - dy);
+                if !(1..=8).contains(&x) || !(1..=8).contains(&y) {
+                    continue;
+                }
+                let from = (x as u64, y as u64);
+                let f = from.normal();
+                if occupied[f] {
+                    continue;
+                }
+                let mut before = self.board;
+                if before.remove(p, t).is_err() || before.put(p, f).is_err() {
+                    continue;
+                }
+                let reaches = before.move_mask_raw(p).is_ok_and(|mask| mask.num[t]);
+                if reaches {
+                    out.push(Unmove { piece: p, from, to });
+                }
+            }
+        }
+        out
+    }
+
+    /// Returns the position before unmove, with the mover to move again. Call state and the
+    /// last moved pieces are kept as they are.
+    pub fn unmake(&self, u: Unmove) -> Instance {
+        let mut before = self.scratch();
+        let _ = before.board.remove(u.piece, u.to.normal());
+        let _ = before.board.put(u.piece, u.from.normal());
+        before.side = !self.side;
+        before.outcome = None;
+        before
+    }
+
+    /// Plays move for the side to move. Returns false if it was rejected.
+    pub fn play(&mut self, m: Move) -> bool {
+        match m {
+            Move::Step(p, pos) => self.make_move(p, pos),
+            Move::Call => {
+                if self.outcome.is_some() {
+                    return false;
+                }
+                self.call_win();
+                true
+            }
+        }
+    }
+}
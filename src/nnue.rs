@@ -0,0 +1,236 @@
+/*!
+Small efficiently updatable neural network evaluation, run on the CPU.
+
+Inputs are one feature per piece kind × side × tile, seen from each side's perspective:
+own pieces first, and orange's tiles flipped so both sides look up the board. The first
+layer (`1024 -> HIDDEN` per perspective) is kept in an [`Accumulator`] that moves only
+update for the few features they change. The output layer reads the clipped side to move
+half followed by the opponent half.
+
+Network file layout, little endian: `GTCN`, hidden size (u32), first layer weights
+(i16, feature major), first layer biases (i16), output weights (i16, 2 × hidden), output
+bias (i32).
+*/
+
+use std::{fs, sync::Arc};
+
+use bitvec::{prelude::Msb0, view::BitViewSized};
+
+use crate::{
+    board::Board,
+    piece::{Piece, Side},
+};
+
+pub const FEATURES: usize = 8 * 2 * 64;
+/// Clipped ReLU ceiling and first layer scale
+pub const QA: i32 = 255;
+/// Output layer scale
+pub const QB: i32 = 64;
+/// Converts network output to evaluation units
+pub const SCALE: i32 = 400;
+
+const MAGIC: &[u8; 4] = b"GTCN";
+
+#[derive(Clone, Debug)]
+pub struct Network {
+    pub hidden: usize,
+    pub ft_weights: Vec<i16>,
+    pub ft_bias: Vec<i16>,
+    pub out_weights: Vec<i16>,
+    pub out_bias: i32,
+}
+
+/// Returns feature index of piece on normalized tile from perspective
+pub fn feature(perspective: Side, p: Piece, tile: usize) -> Option<usize> {
+    let kind = p.index()? as usize;
+    let theirs = (p.side()? != perspective) as usize;
+    let tile = match perspective {
+        Side::White => tile,
+        Side::Orange => tile ^ 56,
+    };
+    Some((theirs * 8 + kind) * 64 + tile)
+}
+
+/// Returns every piece on board with its normalized tile
+fn pieces(board: &Board) -> Vec<(Piece, usize)> {
+    board
+        .board_state()
+        .iter_ones()
+        .map(|i| (board.piece_from_norm(i as u64), i))
+        .collect()
+}
+
+impl Network {
+    /// Returns network with every weight zero
+    pub fn zeroed(hidden: usize) -> Self {
+        Network {
+            hidden,
+            ft_weights: vec![0; FEATURES * hidden],
+            ft_bias: vec![0; hidden],
+            out_weights: vec![0; 2 * hidden],
+            out_bias: 0,
+        }
+    }
+
+    pub fn parse(raw: &[u8]) -> Result<Self, &'static str> {
+        if raw.len() < 8 || &raw[0..4] != MAGIC {
+            return Err("not a network file");
+        }
+        let hidden = u32::from_le_bytes([raw[4], raw[5], raw[6], raw[7]]) as usize;
+        let shorts = FEATURES * hidden + hidden + 2 * hidden;
+        if raw.len() != 8 + shorts * 2 + 4 {
+            return Err("network file has the wrong size");
+        }
+        let mut values = raw[8..8 + shorts * 2]
+            .chunks_exact(2)
+            .map(|c| i16::from_le_bytes([c[0], c[1]]));
+        let mut take = |n: usize| values.by_ref().take(n).collect::<Vec<i16>>();
+        let ft_weights = take(FEATURES * hidden);
+        let ft_bias = take(hidden);
+        let out_weights = take(2 * hidden);
+        let tail = &raw[raw.len() - 4..];
+        Ok(Network {
+            hidden,
+            ft_weights,
+            ft_bias,
+            out_weights,
+            out_bias: i32::from_le_bytes([tail[0], tail[1], tail[2], tail[3]]),
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = MAGIC.to_vec();
+        out.extend((self.hidden as u32).to_le_bytes());
+        for v in self
+            .ft_weights
+            .iter()
+            .chain(self.ft_bias.iter())
+            .chain(self.out_weights.iter())
+        {
+            out.extend(v.to_le_bytes());
+        }
+        out.extend(self.out_bias.to_le_bytes());
+        out
+    }
+
+    pub fn load(path: &str) -> Result<Self, &'static str> {
+        let raw = fs::read(path).map_err(|_| "Couldn't read network file.")?;
+        Network::parse(&raw)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), &'static str> {
+        fs::write(path, self.to_bytes()).map_err(|_| "Failed to write network.")
+    }
+
+    /// Scores accumulated position for side to move
+    pub fn evaluate(&self, acc: &Accumulator, side: Side) -> i32 {
+        let (us, them) = match side {
+            Side::White => (&acc.white, &acc.orange),
+            Side::Orange => (&acc.orange, &acc.white),
+        };
+        let mut sum: i64 = 0;
+        for (i, v) in us.iter().chain(them.iter()).enumerate() {
+            let clipped = (*v as i32).clamp(0, QA) as i64;
+            sum += clipped * self.out_weights[i] as i64;
+        }
+        ((sum + self.out_bias as i64) * SCALE as i64 / (QA * QB) as i64) as i32
+    }
+}
+
+/// First layer output for both perspectives
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Accumulator {
+    pub white: Vec<i16>,
+    pub orange: Vec<i16>,
+}
+
+impl Accumulator {
+    /// Computes accumulator for board from scratch
+    pub fn new(net: &Network, board: &Board) -> Self {
+        let mut acc = Accumulator {
+            white: net.ft_bias.clone(),
+            orange: net.ft_bias.clone(),
+        };
+        for (p, tile) in pieces(board) {
+            acc.add(net, p, tile);
+        }
+        acc
+    }
+
+    fn apply(&mut self, net: &Network, p: Piece, tile: usize, sign: i16) {
+        for (perspective, half) in [
+            (Side::White, &mut self.white),
+            (Side::Orange, &mut self.orange),
+        ] {
+            if let Some(f) = feature(perspective, p, tile) {
+                let row = &net.ft_weights[f * net.hidden..(f + 1) * net.hidden];
+                for (h, w) in half.iter_mut().zip(row) {
+                    *h = h.wrapping_add(sign.wrapping_mul(*w));
+                }
+            }
+        }
+    }
+
+    pub fn add(&mut self, net: &Network, p: Piece, tile: usize) {
+        self.apply(net, p, tile, 1);
+    }
+
+    pub fn remove(&mut self, net: &Network, p: Piece, tile: usize) {
+        self.apply(net, p, tile, -1);
+    }
+
+    /// Updates accumulator of `before` to match `after` touching only changed tiles
+    pub fn update(&mut self, net: &Network, before: &Board, after: &Board) {
+        let changed = (before.white.num.data ^ after.white.num.data)
+            | (before.orange.num.data ^ after.orange.num.data)
+            | (before.goats.num.data ^ after.goats.num.data)
+            | (before.horses.num.data ^ after.horses.num.data)
+            | (before.sloths.num.data ^ after.sloths.num.data)
+            | (before.birds.num.data ^ after.birds.num.data)
+            | (before.tigers.num.data ^ after.tigers.num.data)
+            | (before.otters.num.data ^ after.otters.num.data)
+            | (before.snakes.num.data ^ after.snakes.num.data)
+            | (before.mantis_shrimps.num.data ^ after.mantis_shrimps.num.data);
+        for i in changed.into_bitarray::<Msb0>().iter_ones() {
+            let old = before.piece_from_norm(i as u64);
+            let new = after.piece_from_norm(i as u64);
+            if old != Piece::None {
+                self.remove(net, old, i);
+            }
+            if new != Piece::None {
+                self.add(net, new, i);
+            }
+        }
+    }
+}
+
+/// Accumulators along the current search line, one per ply
+#[derive(Clone, Debug)]
+pub struct AccumulatorStack {
+    net: Arc<Network>,
+    stack: Vec<Accumulator>,
+}
+
+impl AccumulatorStack {
+    pub fn new(net: Arc<Network>, root: &Board) -> Self {
+        let root = Accumulator::new(&net, root);
+        AccumulatorStack {
+            net,
+            stack: vec![root],
+        }
+    }
+
+    /// Sets accumulator at ply to its parent's updated from `before` to `after`
+    pub fn update(&mut self, ply: usize, before: &Board, after: &Board) {
+        if self.stack.len() <= ply {
+            self.stack.resize(ply + 1, self.stack[0].clone());
+        }
+        let (parents, rest) = self.stack.split_at_mut(ply);
+        rest[0].clone_from(&parents[ply - 1]);
+        rest[0].update(&self.net, before, after);
+    }
+
+    pub fn evaluate(&self, ply: usize, side: Side) -> i32 {
+        self.net.evaluate(&self.stack[ply], side)
+    }
+}
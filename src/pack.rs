@@ -0,0 +1,201 @@
+/*!
+Fixed-size binary packing of an [`Instance`] for large position datasets.
+
+A packed position is [`PACKED_SIZE`] bytes:
+
+- bytes 0-7: occupancy bitboard (big-endian, same bit order as [`BitBoard`])
+- bytes 8-15: one nibble per occupied tile in ascending normalized order, high nibble first.
+  The nibble is `side << 3 | kind` where kind is [`Piece::index`]
+- byte 16: side to move (bit 0), white/orange call (bits 1-2), white/orange miss-call (bits 3-4)
+- byte 17: last moved piece kind + 1 for white (high nibble) and orange (low nibble), 0 for none
+
+Move logs are not packed.
+*/
+
+use std::io::{self, Read, Write};
+
+use bitvec::{prelude::Msb0, view::BitViewSized};
+
+use crate::{
+    bitboard::BitBoard,
+    board::Board,
+    game::Instance,
+    piece::{Piece, Side},
+};
+
+pub const PACKED_SIZE: usize = 18;
+
+/// The most pieces a packed position can hold
+pub const MAX_PIECES: usize = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PackedPosition(pub [u8; PACKED_SIZE]);
+
+fn side_bit(s: Side) -> u8 {
+    match s {
+        Side::Orange => 0,
+        Side::White => 1,
+    }
+}
+
+fn bit_side(b: u8) -> Side {
+    if b & 1 == 1 {
+        Side::White
+    } else {
+        Side::Orange
+    }
+}
+
+impl PackedPosition {
+    /// Packs board, side to move and call state of instance
+    pub fn encode(ctx: &Instance) -> Result<Self, &'static str> {
+        let mut out = [0u8; PACKED_SIZE];
+        let occupancy = BitBoard::from_bitarray(ctx.board.board_state());
+        if occupancy.num.count_ones() > MAX_PIECES {
+            return Err("too many pieces to pack");
+        }
+        out[0..8].copy_from_slice(&occupancy.num.data.to_be_bytes());
+
+        for (n, i) in occupancy.num.iter_ones().enumerate() {
+            let piece = ctx.board.piece_from_norm(i as u64);
+            let (kind, side) = match (piece.index(), piece.side()) {
+                (Some(k), Some(s)) => (k, s),
+                _ => return Err("Invalid piece"),
+            };
+            let nibble = side_bit(side) << 3 | kind;
+            out[8 + n / 2] |= if n % 2 == 0 { nibble << 4 } else { nibble };
+        }
+
+        let mut flags = side_bit(ctx.side);
+        if ctx.call[&Side::White] {
+            flags |= 1 << 1;
+        }
+        if ctx.call[&Side::Orange] {
+            flags |= 1 << 2;
+        }
+        if ctx.miss_call[&Side::White] {
+            flags |= 1 << 3;
+        }
+        if ctx.miss_call[&Side::Orange] {
+            flags |= 1 << 4;
+        }
+        out[16] = flags;
+
+        let last = |s: Side| ctx.last_move[&s].index().map_or(0, |k| k + 1);
+        out[17] = last(Side::White) << 4 | last(Side::Orange);
+
+        Ok(PackedPosition(out))
+    }
+
+    /// Unpacks into a fresh instance with an empty move log
+    pub fn decode(&self) -> Result<Instance, &'static str> {
+        let bytes = self.0;
+        let mut raw = [0u8; 8];
+        raw.copy_from_slice(&bytes[0..8]);
+        let occupancy = BitBoard::from_bitarray(u64::from_be_bytes(raw).into_bitarray::<Msb0>());
+        if occupancy.num.count_ones() > MAX_PIECES {
+            return Err("too many pieces to unpack");
+        }
+
+        let mut board = Board::blank();
+        for (n, i) in occupancy.num.iter_ones().enumerate() {
+            let byte = bytes[8 + n / 2];
+            let nibble = if n % 2 == 0 { byte >> 4 } else { byte & 0x0f };
+            board.put(Piece::from_index(nibble & 0b111, bit_side(nibble >> 3)), i)?;
+        }
+
+        let mut ctx = Instance::blank();
+        ctx.board = board;
+        ctx.side = bit_side(bytes[16]);
+        ctx.call.insert(Side::White, bytes[16] & 1 << 1 != 0);
+        ctx.call.insert(Side::Orange, bytes[16] & 1 << 2 != 0);
+        ctx.miss_call.insert(Side::White, bytes[16] & 1 << 3 != 0);
+        ctx.miss_call.insert(Side::Orange, bytes[16] & 1 << 4 != 0);
+
+        let last = |nibble: u8, s: Side| match nibble {
+            0 => Piece::None,
+            k => Piece::from_index(k - 1, s),
+        };
+        ctx.last_move
+            .insert(Side::White, last(bytes[17] >> 4, Side::White));
+        ctx.last_move
+            .insert(Side::Orange, last(bytes[17] & 0x0f, Side::Orange));
+
+        Ok(ctx)
+    }
+}
+
+/// Writes packed positions back to back
+pub struct PackWriter<W: Write> {
+    inner: W,
+}
+
+impl<W: Write> PackWriter<W> {
+    pub fn new(inner: W) -> Self {
+        PackWriter { inner }
+    }
+
+    /// Packs and writes a single position
+    pub fn write(&mut self, ctx: &Instance) -> io::Result<()> {
+        let packed = PackedPosition::encode(ctx)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        self.inner.write_all(&packed.0)
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+
+    /// Returns the underlying writer
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+/// Reads packed positions until the end of the stream
+pub struct PackReader<R: Read> {
+    inner: R,
+}
+
+impl<R: Read> PackReader<R> {
+    pub fn new(inner: R) -> Self {
+        PackReader { inner }
+    }
+
+    /// Reads the next packed position. Returns `None` at a clean end of stream.
+    pub fn read_packed(&mut self) -> io::Result<Option<PackedPosition>> {
+        let mut buf = [0u8; PACKED_SIZE];
+        let mut filled = 0;
+        while filled < PACKED_SIZE {
+            match self.inner.read(&mut buf[filled..]) {
+                Ok(0) if filled == 0 => return Ok(None),
+                Ok(0) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "truncated packed position",
+                    ))
+                }
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(Some(PackedPosition(buf)))
+    }
+}
+
+impl<R: Read> Iterator for PackReader<R> {
+    type Item = io::Result<Instance>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.read_packed() {
+            Ok(Some(packed)) => Some(
+                packed
+                    .decode()
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+            ),
+            Ok(None) => None,
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
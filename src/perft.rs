@@ -0,0 +1,61 @@
+/*!
+Counts leaf nodes of the move tree to validate move generation and make/unmake.
+
+Every legal turn is a branch, including the win call, so counts are higher than the number
+of piece steps alone. Finished games are leaves with no children. [`Instance::legal_moves`]
+only generates turns [`Instance::play`] accepts, so leaves are counted without playing
+them.
+*/
+
+use rayon::prelude::*;
+
+use crate::{game::Instance, movegen::Move};
+
+/// Counts positions reachable in exactly `depth` turns
+pub fn perft(ctx: &Instance, depth: u32) -> u64 {
+    count(&ctx.scratch(), depth)
+}
+
+/// `perft` on a copy without move log, clocks or observers, so they aren't copied or
+/// notified at every node
+fn count(ctx: &Instance, depth: u32) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    let moves = ctx.legal_moves();
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+    let mut nodes = 0;
+    for m in moves {
+        let mut child = ctx.scratch();
+        child.play(m);
+        nodes += count(&child, depth - 1);
+    }
+    nodes
+}
+
+/// Counts positions reachable in exactly `depth` turns for each root move.
+/// Root moves are counted in parallel.
+pub fn divide(ctx: &Instance, depth: u32) -> Vec<(Move, u64)> {
+    let root = ctx.scratch();
+    if depth == 0 {
+        return vec![];
+    }
+    root.legal_moves()
+        .into_par_iter()
+        .map(|m| {
+            let mut child = root.scratch();
+            child.play(m);
+            (m, count(&child, depth - 1))
+        })
+        .collect()
+}
+
+/// `perft` with root moves counted in parallel
+pub fn perft_parallel(ctx: &Instance, depth: u32) -> u64 {
+    if depth < 2 {
+        return perft(ctx, depth);
+    }
+    divide(ctx, depth).iter().map(|(_, n)| n).sum()
+}
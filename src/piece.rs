@@ -1,3 +1,5 @@
+use alloc::string::{String, ToString};
+
 use strum::{Display, EnumString};
 
 #[derive(
@@ -9,7 +11,18 @@ pub enum Side {
     White,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Copy, EnumString, Display)]
+impl core::ops::Not for Side {
+    type Output = Self;
+
+    fn not(self) -> Self::Output {
+        match self {
+            Side::Orange => Side::White,
+            Side::White => Side::Orange,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Copy, EnumString, Display, Hash)]
 pub enum Piece {
     None,
     Goat(Side),
@@ -23,6 +36,48 @@ pub enum Piece {
 }
 
 impl Piece {
+    /// Returns piece of the kind at index `i` (declaration order, goat first) for side
+    pub fn from_index(i: u8, s: Side) -> Piece {
+        match i {
+            0 => Piece::Goat(s),
+            1 => Piece::Horse(s),
+            2 => Piece::Sloth(s),
+            3 => Piece::Bird(s),
+            4 => Piece::Tiger(s),
+            5 => Piece::Otter(s),
+            6 => Piece::Snake(s),
+            7 => Piece::MantisShrimp(s),
+            _ => Piece::None,
+        }
+    }
+    /// Returns index of the piece kind (declaration order, goat first)
+    pub fn index(self) -> Option<u8> {
+        match self {
+            Piece::None => None,
+            Piece::Goat(_) => Some(0),
+            Piece::Horse(_) => Some(1),
+            Piece::Sloth(_) => Some(2),
+            Piece::Bird(_) => Some(3),
+            Piece::Tiger(_) => Some(4),
+            Piece::Otter(_) => Some(5),
+            Piece::Snake(_) => Some(6),
+            Piece::MantisShrimp(_) => Some(7),
+        }
+    }
+    /// Returns side of the piece
+    pub fn side(self) -> Option<Side> {
+        match self {
+            Piece::None => None,
+            Piece::Goat(s)
+            | Piece::Horse(s)
+            | Piece::Sloth(s)
+            | Piece::Bird(s)
+            | Piece::Tiger(s)
+            | Piece::Otter(s)
+            | Piece::Snake(s)
+            | Piece::MantisShrimp(s) => Some(s),
+        }
+    }
     /// Capitalizes encoded piece depending on side
     pub fn side_encode(self, p: char, s: Side) -> String {
         match s {
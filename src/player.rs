@@ -0,0 +1,432 @@
+/*!
+Computer players behind one interface, and a harness to play them against each other.
+
+Players are built from short specs so front ends can name them:
+
+- `random`: any legal turn
+- `greedy`: wins on the spot once it has called, otherwise takes the best capture,
+  passives first
+- `lookahead[:depth]`: alpha-beta [`search`] to a fixed depth
+- `mcts[:iterations]`: Monte Carlo tree search
+- `skill[:level]`: adjustable strength from 0 to [`MAX_SKILL`], see [`SkillPlayer`]
+
+Searching players score leaves as the [`PlayerConfig`] passed to [`from_spec`] says.
+
+Every player built from a spec calls wins through [`AutoCall`], set with a `,call=` option:
+`always` (the default except for `skill`), `never`, or the chance of calling, e.g.
+`random,call=0.5`. A player that doesn't call plays the winning step anyway, a deliberate
+miss-call.
+
+A `,book` option makes the player play from the opening book in the [`PlayerConfig`] while
+it has the position, see [`BookMoves`].
+*/
+
+use std::{fmt, str::FromStr, sync::Arc};
+
+use crate::{
+    book::Book,
+    game::{Instance, Outcome},
+    mcts::{mcts, MctsLimits},
+    movegen::Move,
+    piece::{Piece, Side},
+    record::GameRecord,
+    rng::Rng,
+    search::{rank_moves, search, Limits, DEFAULT_DEPTH},
+};
+
+pub trait Player: Send {
+    /// Spec the player was built from
+    fn name(&self) -> String;
+
+    /// Picks a turn for the side to move: a step or a win call. Returns `None` if there is
+    /// nothing to play.
+    fn choose(&mut self, ctx: &Instance) -> Option<Move>;
+}
+
+pub struct RandomPlayer {
+    rng: Rng,
+}
+
+impl RandomPlayer {
+    pub fn new(rng: Rng) -> Self {
+        RandomPlayer { rng }
+    }
+}
+
+impl Player for RandomPlayer {
+    fn name(&self) -> String {
+        "random".to_string()
+    }
+
+    fn choose(&mut self, ctx: &Instance) -> Option<Move> {
+        let moves = ctx.legal_moves();
+        moves.get(self.rng.below(moves.len())).copied()
+    }
+}
+
+pub struct GreedyPlayer {
+    rng: Rng,
+}
+
+impl GreedyPlayer {
+    pub fn new(rng: Rng) -> Self {
+        GreedyPlayer { rng }
+    }
+}
+
+/// Ranks what a step takes: passives above aggressives above nothing
+fn capture_rank(ctx: &Instance, m: Move) -> u8 {
+    let target = match m {
+        Move::Step(_, to) => ctx.piece_at(to),
+        Move::Call => return 0,
+    };
+    match target.index() {
+        _ if target == Piece::None || target.side() == Some(ctx.side) => 0,
+        Some(k) if k < 4 => 2,
+        _ => 1,
+    }
+}
+
+impl Player for GreedyPlayer {
+    fn name(&self) -> String {
+        "greedy".to_string()
+    }
+
+    fn choose(&mut self, ctx: &Instance) -> Option<Move> {
+        let moves = ctx.legal_moves();
+        if ctx.call[&ctx.side] {
+            if let Some(m) = ctx.winning_steps().first() {
+                return Some(*m);
+            }
+        }
+
+        let best = moves.iter().map(|m| capture_rank(ctx, *m)).max()?;
+        let top: Vec<Move> = moves
+            .iter()
+            .copied()
+            .filter(|m| capture_rank(ctx, *m) == best && *m != Move::Call)
+            .collect();
+        if top.is_empty() {
+            return moves.first().copied();
+        }
+        Some(top[self.rng.below(top.len())])
+    }
+}
+
+pub struct LookaheadPlayer {
+    pub limits: Limits,
+}
+
+impl Player for LookaheadPlayer {
+    fn name(&self) -> String {
+        format!("lookahead:{}", self.limits.depth.unwrap_or(DEFAULT_DEPTH))
+    }
+
+    fn choose(&mut self, ctx: &Instance) -> Option<Move> {
+        search(ctx, &self.limits, |_| {}).best
+    }
+}
+
+pub struct MctsPlayer {
+    pub iterations: u64,
+    rng: Rng,
+}
+
+impl MctsPlayer {
+    pub fn new(iterations: u64, rng: Rng) -> Self {
+        MctsPlayer { iterations, rng }
+    }
+}
+
+impl Player for MctsPlayer {
+    fn name(&self) -> String {
+        format!("mcts:{}", self.iterations)
+    }
+
+    fn choose(&mut self, ctx: &Instance) -> Option<Move> {
+        let limits = MctsLimits {
+            iterations: Some(self.iterations),
+            movetime: None,
+        };
+        mcts(ctx, &limits, &mut self.rng).best
+    }
+}
+
+pub const MAX_SKILL: u8 = 20;
+/// Skill used when a `skill` spec gives none
+pub const DEFAULT_SKILL: u8 = 10;
+
+/// Opponent for casual play. Lower skill searches shallower, picks at random among moves
+/// scoring within a margin of the best, and more often misses a call.
+pub struct SkillPlayer {
+    pub skill: u8,
+    /// searched to [`SkillPlayer::depth`]
+    limits: Limits,
+    rng: Rng,
+}
+
+impl SkillPlayer {
+    /// Builds player of skill, scoring leaves as limits says. Depth and move time of limits
+    /// are replaced by the skill's.
+    pub fn new(skill: u8, limits: Limits, rng: Rng) -> Self {
+        let mut p = SkillPlayer {
+            skill: skill.min(MAX_SKILL),
+            limits,
+            rng,
+        };
+        p.limits.depth = Some(p.depth());
+        p.limits.movetime = None;
+        p
+    }
+
+    /// Search depth, 1 at skill 0 up to 5 at skill 20
+    pub fn depth(&self) -> u32 {
+        1 + self.skill as u32 / 5
+    }
+
+    /// How far below the best score a move may be and still get picked
+    pub fn margin(&self) -> i32 {
+        (MAX_SKILL - self.skill) as i32 * 15
+    }
+
+    /// Chance of calling a win that is one step away, 0.5 at skill 0 up to 1 at skill 20
+    pub fn call_policy(&self) -> CallPolicy {
+        CallPolicy::Chance(0.5 + self.skill as f64 / (2.0 * MAX_SKILL as f64))
+    }
+}
+
+impl Player for SkillPlayer {
+    fn name(&self) -> String {
+        format!("skill:{}", self.skill)
+    }
+
+    fn choose(&mut self, ctx: &Instance) -> Option<Move> {
+        let ranked = rank_moves(ctx, &self.limits, Some(self.margin()));
+        let best = ranked.first()?;
+        if best.mv == Move::Call {
+            return Some(Move::Call);
+        }
+        let near: Vec<Move> = ranked
+            .iter()
+            .filter(|r| r.mv != Move::Call)
+            .map(|r| r.mv)
+            .collect();
+        Some(near[self.rng.below(near.len())])
+    }
+}
+
+/// When a player calls a win that is one step away
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CallPolicy {
+    Always,
+    Never,
+    /// calls with this probability each turn a win is available
+    Chance(f64),
+}
+
+impl FromStr for CallPolicy {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "always" => Ok(CallPolicy::Always),
+            "never" => Ok(CallPolicy::Never),
+            p => match p.parse::<f64>() {
+                Ok(p) if (0.0..=1.0).contains(&p) => Ok(CallPolicy::Chance(p)),
+                _ => Err("call policy must be always, never or a probability"),
+            },
+        }
+    }
+}
+
+impl fmt::Display for CallPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CallPolicy::Always => write!(f, "always"),
+            CallPolicy::Never => write!(f, "never"),
+            CallPolicy::Chance(p) => write!(f, "{}", p),
+        }
+    }
+}
+
+/// Takes over from the wrapped player whenever [`Instance::can_call`]: calls as the policy
+/// says, or plays a winning step uncalled
+pub struct AutoCall {
+    pub inner: Box<dyn Player>,
+    pub policy: CallPolicy,
+    rng: Rng,
+}
+
+impl AutoCall {
+    pub fn new(inner: Box<dyn Player>, policy: CallPolicy, rng: Rng) -> Self {
+        AutoCall { inner, policy, rng }
+    }
+}
+
+impl Player for AutoCall {
+    fn name(&self) -> String {
+        match self.policy {
+            CallPolicy::Always => self.inner.name(),
+            p => format!("{},call={}", self.inner.name(), p),
+        }
+    }
+
+    fn choose(&mut self, ctx: &Instance) -> Option<Move> {
+        if !ctx.can_call() {
+            return self.inner.choose(ctx);
+        }
+        let call = match self.policy {
+            CallPolicy::Always => true,
+            CallPolicy::Never => false,
+            CallPolicy::Chance(p) => self.rng.chance(p),
+        };
+        if call {
+            return Some(Move::Call);
+        }
+        let steps = ctx.winning_steps();
+        steps.get(self.rng.below(steps.len())).copied()
+    }
+}
+
+/// Plays a weighted random move from the opening book while the position is in it, and
+/// leaves the rest to the wrapped player. Without a book every move is left to it.
+pub struct BookMoves {
+    pub inner: Box<dyn Player>,
+    pub book: Option<Arc<Book>>,
+    rng: Rng,
+}
+
+impl BookMoves {
+    pub fn new(inner: Box<dyn Player>, book: Option<Arc<Book>>, rng: Rng) -> Self {
+        BookMoves { inner, book, rng }
+    }
+}
+
+impl Player for BookMoves {
+    fn name(&self) -> String {
+        format!("{},book", self.inner.name())
+    }
+
+    fn choose(&mut self, ctx: &Instance) -> Option<Move> {
+        let from_book = self.book.as_ref().and_then(|b| b.pick(ctx, &mut self.rng));
+        from_book.or_else(|| self.inner.choose(ctx))
+    }
+}
+
+/// Engine settings for the players built from specs
+#[derive(Clone, Debug, Default)]
+pub struct PlayerConfig {
+    /// what searching players score leaves with. Depth and move time come from the spec.
+    pub limits: Limits,
+    /// book the `,book` option plays from
+    pub book: Option<Arc<Book>>,
+}
+
+/// Builds a player from its spec, e.g. `lookahead:3,call=0.8,book`. Random choices are
+/// drawn from seed.
+pub fn from_spec(
+    spec: &str,
+    seed: u64,
+    cfg: &PlayerConfig,
+) -> Result<Box<dyn Player>, &'static str> {
+    let mut options = spec.split(',');
+    let base = options.next().unwrap_or_default();
+    let (inner, mut policy) = base_player(base, seed, cfg)?;
+    let mut use_book = false;
+    for option in options {
+        match option.split_once('=') {
+            Some(("call", p)) => policy = p.parse()?,
+            None if option == "book" => use_book = true,
+            _ => return Err("unknown player option"),
+        }
+    }
+    let player = Box::new(AutoCall::new(inner, policy, Rng::new(seed.rotate_left(32))));
+    if use_book {
+        return Ok(Box::new(BookMoves::new(
+            player,
+            cfg.book.clone(),
+            Rng::new(seed.rotate_left(16)),
+        )));
+    }
+    Ok(player)
+}
+
+/// Builds the player a spec names along with its default call policy
+fn base_player(
+    spec: &str,
+    seed: u64,
+    cfg: &PlayerConfig,
+) -> Result<(Box<dyn Player>, CallPolicy), &'static str> {
+    let (kind, arg) = match spec.split_once(':') {
+        Some((kind, arg)) => (kind, Some(arg)),
+        None => (spec, None),
+    };
+    let number = |default: u64| match arg {
+        Some(a) => a.parse::<u64>().map_err(|_| "invalid player setting"),
+        None => Ok(default),
+    };
+    let rng = Rng::new(seed);
+    let player: Box<dyn Player> = match kind {
+        "random" => Box::new(RandomPlayer::new(rng)),
+        "greedy" => Box::new(GreedyPlayer::new(rng)),
+        "lookahead" => Box::new(LookaheadPlayer {
+            limits: Limits {
+                depth: Some(number(DEFAULT_DEPTH as u64)? as u32),
+                movetime: None,
+                ..cfg.limits.clone()
+            },
+        }),
+        "mcts" => Box::new(MctsPlayer::new(
+            number(crate::mcts::DEFAULT_ITERATIONS)?,
+            rng,
+        )),
+        "skill" => {
+            let skill = number(DEFAULT_SKILL as u64)?;
+            if skill > MAX_SKILL as u64 {
+                return Err("skill goes from 0 to 20");
+            }
+            let p = SkillPlayer::new(skill as u8, cfg.limits.clone(), rng);
+            let policy = p.call_policy();
+            return Ok((Box::new(p), policy));
+        }
+        _ => return Err(
+            "unknown player, expected random, greedy, lookahead[:depth], mcts[:iterations] or skill[:level]",
+        ),
+    };
+    Ok((player, CallPolicy::Always))
+}
+
+#[derive(Clone, Debug)]
+pub struct GameResult {
+    pub record: GameRecord,
+    /// `None` if the game hit the turn limit or a player had no move
+    pub outcome: Option<Outcome>,
+}
+
+/// Plays a game from start between two players, giving up after `max_plies` turns
+pub fn play_game(
+    start: &Instance,
+    white: &mut dyn Player,
+    orange: &mut dyn Player,
+    max_plies: usize,
+) -> GameResult {
+    let mut ctx = start.scratch();
+    let mut record = GameRecord {
+        start: start.scratch(),
+        moves: vec![],
+    };
+    while ctx.outcome.is_none() && record.moves.len() < max_plies {
+        let choice = match ctx.side {
+            Side::White => white.choose(&ctx),
+            Side::Orange => orange.choose(&ctx),
+        };
+        match choice {
+            Some(m) if ctx.play(m) => record.moves.push(m),
+            _ => break,
+        }
+    }
+    GameResult {
+        record,
+        outcome: ctx.outcome,
+    }
+}
@@ -5,6 +5,8 @@ Normalized: a single unsigned integer repersenting an index of a bit in a bitboa
 regular or non-normalized: a tuple containing a non-zero inclusive x and y (or column and row) pair of board position
 */
 
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
 
 pub type Position = (u64, u64);
 
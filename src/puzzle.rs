@@ -0,0 +1,345 @@
+/*!
+Puzzles mined from games: positions where exactly one move wins by force, or exactly one
+move keeps the opponent from winning by force.
+
+A puzzle file has one puzzle per line, `<tiles> <side> <calls>; <solution>; <theme>`.
+`calls` lists the sides that have called (`W`, `O`, `WO`, or `-` for neither), the
+solution is in move log notation separated by commas and the theme is one of
+`edge alignment`, `passive elimination` or `miss-call trap`. Blank lines and lines
+starting with `#` are skipped.
+
+```text
+2tolm2/g7/h7/s7/i7/8/8/GHTOLMSI White -; c, G g1, i f1, G f2, s e1; miss-call trap
+```
+*/
+
+use std::{collections::HashSet, fmt, fs, str::FromStr};
+
+use rayon::prelude::*;
+
+use crate::{
+    board::Board,
+    book,
+    game::{Instance, Outcome, WinReason},
+    movegen::Move,
+    piece::Side,
+    record::GameRecord,
+    search::{format_pv, is_win_score, rank_moves, search, Limits, RankedMove},
+};
+
+/// Turn limit of the forced lines looked for when none is given
+pub const DEFAULT_MAX_DEPTH: u32 = 5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Theme {
+    EdgeAlignment,
+    PassiveElimination,
+    /// the win has to be called first, and a move that wins on the spot without a call
+    /// would be penalized
+    MissCallTrap,
+}
+
+impl fmt::Display for Theme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Theme::EdgeAlignment => "edge alignment",
+            Theme::PassiveElimination => "passive elimination",
+            Theme::MissCallTrap => "miss-call trap",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Theme {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "edge alignment" => Ok(Theme::EdgeAlignment),
+            "passive elimination" => Ok(Theme::PassiveElimination),
+            "miss-call trap" => Ok(Theme::MissCallTrap),
+            _ => Err("unknown puzzle theme"),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Puzzle {
+    /// board, side to move and calls; no miss-call is pending
+    pub position: Instance,
+    /// the only move, followed by the rest of the forced line for a win
+    pub solution: Vec<Move>,
+    pub theme: Theme,
+}
+
+impl Puzzle {
+    /// Returns true if the solution wins the game, false if it only holds off a loss
+    pub fn is_win(&self) -> bool {
+        let mut ctx = self.position.scratch();
+        for m in self.solution.iter() {
+            if !ctx.play(*m) {
+                return false;
+            }
+        }
+        matches!(ctx.outcome, Some(Outcome::Win(s, _)) if s == self.position.side)
+    }
+}
+
+fn calls(ctx: &Instance) -> &'static str {
+    match (ctx.call[&Side::White], ctx.call[&Side::Orange]) {
+        (false, false) => "-",
+        (true, false) => "W",
+        (false, true) => "O",
+        (true, true) => "WO",
+    }
+}
+
+impl fmt::Display for Puzzle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {}; {}; {}",
+            self.position.board.encode(),
+            self.position.side,
+            calls(&self.position),
+            format_pv(&self.solution),
+            self.theme
+        )
+    }
+}
+
+impl FromStr for Puzzle {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.split(';').collect();
+        if parts.len() != 3 {
+            return Err("puzzle needs a position, a solution and a theme");
+        }
+        let head: Vec<&str> = parts[0].split_whitespace().collect();
+        if head.len() != 3 {
+            return Err("puzzle position needs tiles, side and calls");
+        }
+        let mut position = Instance::blank();
+        position.board = Board::decode(head[0].to_string())?;
+        position.side = Side::from_str(head[1]).map_err(|_| "invalid side")?;
+        let called = match head[2] {
+            "-" => (false, false),
+            "W" => (true, false),
+            "O" => (false, true),
+            "WO" => (true, true),
+            _ => return Err("invalid calls"),
+        };
+        position.call.insert(Side::White, called.0);
+        position.call.insert(Side::Orange, called.1);
+
+        let solution = parts[1]
+            .split(',')
+            .filter(|m| !m.trim().is_empty())
+            .map(Move::decode)
+            .collect::<Result<Vec<Move>, &'static str>>()?;
+        if solution.is_empty() {
+            return Err("puzzle has no solution");
+        }
+        Ok(Puzzle {
+            position,
+            solution,
+            theme: parts[2].parse()?,
+        })
+    }
+}
+
+/// Reads a puzzle file
+pub fn load(path: &str) -> Result<Vec<Puzzle>, &'static str> {
+    let raw = fs::read_to_string(path).map_err(|_| "Couldn't read puzzle file.")?;
+    raw.lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(Puzzle::from_str)
+        .collect()
+}
+
+pub fn save(path: &str, puzzles: &[Puzzle]) -> Result<(), &'static str> {
+    let out: String = puzzles.iter().map(|p| format!("{}\n", p)).collect();
+    fs::write(path, out).map_err(|_| "Failed to write puzzles.")
+}
+
+/// Puzzles solved out of those tried, kept between trainer sessions as `<solved>
+/// <attempted>`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    pub solved: u32,
+    pub attempted: u32,
+}
+
+impl Stats {
+    pub fn add(&mut self, solved: bool) {
+        self.attempted += 1;
+        if solved {
+            self.solved += 1;
+        }
+    }
+
+    /// Share of tried puzzles solved, in percent
+    pub fn rate(&self) -> f64 {
+        self.solved as f64 * 100.0 / self.attempted.max(1) as f64
+    }
+
+    pub fn parse(raw: &str) -> Result<Self, &'static str> {
+        let parts: Vec<u32> = raw
+            .split_whitespace()
+            .map(|n| n.parse::<u32>().map_err(|_| "invalid puzzle stats"))
+            .collect::<Result<Vec<u32>, &'static str>>()?;
+        match parts.as_slice() {
+            [solved, attempted] if solved <= attempted => Ok(Stats {
+                solved: *solved,
+                attempted: *attempted,
+            }),
+            _ => Err("invalid puzzle stats"),
+        }
+    }
+
+    /// Reads stats from path, starting over if there are none yet
+    pub fn load(path: &str) -> Result<Self, &'static str> {
+        match fs::read_to_string(path) {
+            Ok(raw) => Stats::parse(&raw),
+            Err(_) => Ok(Stats::default()),
+        }
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), &'static str> {
+        fs::write(path, format!("{} {}\n", self.solved, self.attempted))
+            .map_err(|_| "Failed to write puzzle stats.")
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}/{} solved ({:.0}%)",
+            self.solved,
+            self.attempted,
+            self.rate()
+        )
+    }
+}
+
+/// Returns how the side winning at the end of line from ctx wins
+fn win_reason(ctx: &Instance, line: &[Move]) -> WinReason {
+    let mut ctx = ctx.scratch();
+    for m in line {
+        ctx.play(*m);
+    }
+    match ctx.outcome {
+        Some(Outcome::Win(_, reason)) => reason,
+        _ => WinReason::EdgeAlignment,
+    }
+}
+
+/// Returns true if side calls at one of its turns in line from ctx
+fn calls_in_line(ctx: &Instance, line: &[Move], side: Side) -> bool {
+    let mut ctx = ctx.scratch();
+    for m in line {
+        if *m == Move::Call && ctx.side == side {
+            return true;
+        }
+        if !ctx.play(*m) {
+            return false;
+        }
+    }
+    false
+}
+
+/// Returns true if the side to move of ctx wins by force within depth turns, or would if
+/// it were its turn when passed is set
+fn threatens(ctx: &Instance, depth: u32, passed: bool) -> bool {
+    let mut ctx = ctx.scratch();
+    if passed {
+        ctx.side = !ctx.side;
+    }
+    let limits = Limits {
+        depth: Some(depth),
+        ..Limits::default()
+    };
+    let score = search(&ctx, &limits, |_| {}).score;
+    score > 0 && is_win_score(score)
+}
+
+/// Looks for a puzzle in the position of ctx. Every move is scored exactly by a full
+/// width search to max_depth, so a move that doesn't score a forced win or loss has none
+/// within that many turns. Positions where neither side threatens a forced win are left
+/// out before that, as ranking every move is slow.
+pub fn find(ctx: &Instance, max_depth: u32) -> Option<Puzzle> {
+    let root = ctx.scratch();
+    if root.outcome.is_some() || root.miss_call.values().any(|m| *m) || max_depth < 2 {
+        return None;
+    }
+    if root.legal_moves().len() < 2
+        || !(threatens(&root, max_depth, false) || threatens(&root, max_depth - 1, true))
+    {
+        return None;
+    }
+    let limits = Limits {
+        depth: Some(max_depth),
+        ..Limits::default()
+    };
+    let ranked = rank_moves(&root, &limits, None);
+    let side = root.side;
+    let wins: Vec<&RankedMove> = ranked
+        .iter()
+        .filter(|r| r.score > 0 && is_win_score(r.score))
+        .collect();
+    let holds: Vec<&RankedMove> = ranked
+        .iter()
+        .filter(|r| !(r.score < 0 && is_win_score(r.score)))
+        .collect();
+
+    let (solution, theme) = match (wins.as_slice(), holds.as_slice()) {
+        ([win], _) => {
+            // the winner calls on the way, so winning without the call would be penalized
+            let trap = calls_in_line(&root, &win.pv, side);
+            let theme = match (trap, win_reason(&root, &win.pv)) {
+                (true, _) => Theme::MissCallTrap,
+                (false, WinReason::PassiveElimination) => Theme::PassiveElimination,
+                (false, _) => Theme::EdgeAlignment,
+            };
+            (win.pv.clone(), theme)
+        }
+        ([], [hold]) => {
+            // the threat is what the opponent does against any other move
+            let threat = ranked.iter().find(|r| r.mv != hold.mv)?;
+            let mut child = root.clone();
+            child.play(threat.mv);
+            let theme = if child.miss_call[&side] {
+                // the losing move wins on the spot without a call and is punished for it
+                Theme::MissCallTrap
+            } else if win_reason(&child, &threat.pv[1..]) == WinReason::PassiveElimination {
+                Theme::PassiveElimination
+            } else {
+                Theme::EdgeAlignment
+            };
+            (vec![hold.mv], theme)
+        }
+        _ => return None,
+    };
+    Some(Puzzle {
+        position: root,
+        solution,
+        theme,
+    })
+}
+
+/// Looks for puzzles in every position of games, each position once
+pub fn mine(records: &[GameRecord], max_depth: u32) -> Vec<Puzzle> {
+    let mut seen = HashSet::new();
+    let positions: Vec<Instance> = records
+        .iter()
+        .flat_map(|r| r.replay())
+        .filter(|p| seen.insert(book::hash(p)))
+        .collect();
+    positions
+        .par_iter()
+        .filter_map(|p| find(p, max_depth))
+        .collect()
+}
@@ -0,0 +1,106 @@
+/*!
+Saved games in state file format, as written by `save`.
+
+The first line holds the starting board and side to move, the second line the moves
+separated by commas (`I b8,c,i c7,`).
+*/
+
+use std::{fs, str::FromStr};
+
+use crate::{
+    board::Board,
+    game::{Instance, Outcome},
+    movegen::Move,
+    piece::Side,
+};
+
+#[derive(Clone, Debug)]
+pub struct GameRecord {
+    pub start: Instance,
+    pub moves: Vec<Move>,
+}
+
+impl GameRecord {
+    /// Parses contents of a state file
+    pub fn parse(raw: &str) -> Result<Self, &'static str> {
+        let parts: Vec<&str> = raw.split('\n').collect();
+        let head_parts = parts[0].split_whitespace().collect::<Vec<&str>>();
+        if head_parts.len() < 2 {
+            return Err("invalid state header");
+        }
+        let mut start = Instance::blank();
+        start.board = Board::decode(head_parts[0].to_string())?;
+        start.side = Side::from_str(head_parts[1]).map_err(|_| "invalid side")?;
+        start.states = format!("{}\n", parts[0]);
+
+        let mut moves = vec![];
+        for m in parts.get(1).unwrap_or(&"").split(',') {
+            if m.trim().is_empty() {
+                continue;
+            }
+            moves.push(Move::decode(m)?);
+        }
+        Ok(GameRecord { start, moves })
+    }
+
+    pub fn load(path: &str) -> Result<Self, &'static str> {
+        let raw = fs::read_to_string(path).map_err(|_| "Couldn't read state file.")?;
+        GameRecord::parse(raw.as_str())
+    }
+
+    /// Returns game in state file format
+    pub fn encode(&self) -> String {
+        let mut out = format!("{} {}\n", self.start.board.encode(), self.start.side);
+        for m in self.moves.iter() {
+            out.push_str(&format!("{},", m));
+        }
+        out
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), &'static str> {
+        fs::write(path, self.encode()).map_err(|_| "Failed to write state.")
+    }
+
+    /// Plays the moves from the start, skipping the ones the rules reject. Returns each
+    /// accepted move with the position it was played from, and the final position.
+    fn play_out(&self) -> (Vec<(Instance, Move)>, Instance) {
+        let mut steps = vec![];
+        let mut ctx = self.start.scratch();
+        for m in self.moves.iter() {
+            let before = ctx.scratch();
+            if ctx.play(*m) {
+                steps.push((before, *m));
+            }
+        }
+        (steps, ctx)
+    }
+
+    /// Returns each move the rules accept with the position it was played from, so moves
+    /// line up with their positions even after a rejected one
+    pub fn steps(&self) -> Vec<(Instance, Move)> {
+        self.play_out().0
+    }
+
+    /// Returns the starting position followed by the position after each move. A move the
+    /// rules reject is skipped and the rest still apply, as `read_state_file` does.
+    ///
+    /// Games saved before win calls were logged have no `c,`, so their winning step reads
+    /// as a miss-call. Their result is taken from the final position instead.
+    pub fn replay(&self) -> Vec<Instance> {
+        let (steps, mut last) = self.play_out();
+        let uncalled = !self.moves.contains(&Move::Call);
+        if uncalled && !steps.is_empty() && last.outcome.is_none() {
+            last.outcome = final_outcome(&last);
+        }
+        let mut positions: Vec<Instance> = steps.into_iter().map(|(p, _)| p).collect();
+        positions.push(last);
+        positions
+    }
+}
+
+/// Returns a win for the side that just moved if the board shows one
+fn final_outcome(ctx: &Instance) -> Option<Outcome> {
+    let mut mover = ctx.scratch();
+    mover.side = !ctx.side;
+    mover.win_reason().map(|r| Outcome::Win(mover.side, r))
+}
@@ -1,23 +1,38 @@
-use std::collections::HashMap;
 use std::fs::{self, File};
 use std::io::Write;
+use std::path::PathBuf;
 use std::str::FromStr;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use chrono::Local;
 use random_word::Lang;
 use rustyline::DefaultEditor;
 
-use crate::game::Instance;
+use crate::book;
+use crate::clock::{format_duration, Clocks};
+use crate::eval;
+use crate::game::{Instance, Outcome};
+use crate::mcts::{self, MctsLimits};
+use crate::movegen::Move;
+#[cfg(feature = "nnue")]
+use crate::nnue;
+use crate::perft;
 use crate::piece::Side;
+use crate::player::{self, PlayerConfig};
 use crate::position::{decode_position, Normalizable};
+use crate::puzzle::{self, Puzzle};
+use crate::rng::Rng;
+use crate::search::{self, Limits};
+use crate::solve::{self, Proof};
+use crate::tablebase::{self, TbResult};
 use crate::{board::Board, piece::Piece};
 
 pub fn read_state_file(ctx: &mut Instance, name: String) {
     let raw = fs::read_to_string(name).expect("Couldn't read state file.");
     let binding = raw.clone();
     let parts: Vec<&str> = binding.split("\n").collect();
-    ctx.states = raw.into();
+    ctx.states = raw;
 
     let head_parts = parts[0].split_whitespace().collect::<Vec<&str>>();
     let b_enc = Board::decode(head_parts[0].to_string());
@@ -26,12 +41,17 @@ pub fn read_state_file(ctx: &mut Instance, name: String) {
         return;
     }
     ctx.board = b_enc.unwrap();
+    ctx.outcome = None;
     ctx.side = Side::from_str(head_parts[1]).unwrap();
 
     for m in parts[1].split(",") {
         if m.trim().len() == 0 {
             continue;
         }
+        if m.trim() == "c" {
+            ctx.call_win();
+            continue;
+        }
         let move_parts: Vec<&str> = m.split(" ").collect();
         let (p, _) = Piece::decode(move_parts[0].to_string()).unwrap();
         let pos = decode_position(move_parts[1].to_string());
@@ -44,8 +64,180 @@ pub fn read_state_file(ctx: &mut Instance, name: String) {
 
     println!("From: {}", ctx.board.encode());
 }
-pub fn cmd(mut ctx: &mut Instance, s: &str, prot: bool) {
-    let s = s.trim().split_whitespace().collect::<Vec<&str>>();
+/// Prints perft node count from ctx, broken down per root move if divide is set
+pub fn perft_cmd(ctx: &Instance, depth: u32, divide: bool, prot: bool) {
+    let start = Instant::now();
+    let nodes: u64 = if divide {
+        let counts = perft::divide(ctx, depth);
+        for (m, n) in counts.iter() {
+            println!("{}: {}", m, n);
+        }
+        counts.iter().map(|(_, n)| n).sum()
+    } else {
+        perft::perft_parallel(ctx, depth)
+    };
+    let elapsed = start.elapsed();
+
+    if !prot {
+        let nps = nodes as f64 / elapsed.as_secs_f64().max(1e-9);
+        println!(
+            "nodes: {} ({} ms, {:.0} nps)",
+            nodes,
+            elapsed.as_millis(),
+            nps
+        );
+    } else {
+        println!("{}", nodes);
+    }
+}
+/// Parses `depth N` / `movetime MS` pairs of the go command on top of base
+fn parse_limits(args: &[&str], base: &Limits) -> Result<Limits, &'static str> {
+    let mut limits = Limits {
+        depth: None,
+        movetime: None,
+        ..base.clone()
+    };
+    for pair in args.chunks(2) {
+        let value = pair.get(1).and_then(|v| v.parse::<u64>().ok());
+        match (pair[0], value) {
+            ("depth", Some(d)) => limits.depth = Some(d as u32),
+            ("movetime", Some(ms)) => limits.movetime = Some(Duration::from_millis(ms)),
+            _ => return Err("go [depth <n>] [movetime <ms>]"),
+        }
+    }
+    Ok(limits)
+}
+/// Parses `iterations N` / `movetime MS` pairs of the mcts command
+fn parse_mcts_limits(args: &[&str]) -> Result<MctsLimits, &'static str> {
+    let mut limits = MctsLimits::default();
+    for pair in args.chunks(2) {
+        let value = pair.get(1).and_then(|v| v.parse::<u64>().ok());
+        match (pair[0], value) {
+            ("iterations", Some(n)) => limits.iterations = Some(n),
+            ("movetime", Some(ms)) => limits.movetime = Some(Duration::from_millis(ms)),
+            _ => return Err("mcts [iterations <n>] [movetime <ms>]"),
+        }
+    }
+    Ok(limits)
+}
+
+/// Engine settings changed by REPL commands, kept for the whole session
+#[derive(Clone, Debug, Default)]
+pub struct Settings {
+    /// what `go`, `analyze`, `eval`, `book` and the computer opponent play with
+    pub engine: PlayerConfig,
+    /// directory `probe` reads tables from
+    pub tablebase: Option<PathBuf>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Computer {
+    pub side: Side,
+    /// player spec, see [`player::from_spec`]
+    pub player: String,
+}
+
+impl Computer {
+    /// Picks a move for the side to move
+    pub fn choose(&self, ctx: &Instance, cfg: &PlayerConfig) -> Option<Move> {
+        let mut p = player::from_spec(&self.player, Rng::from_time().next_u64(), cfg).ok()?;
+        p.choose(ctx)
+    }
+}
+
+/// Plays move for the side to move and appends it to the move log
+pub fn play_logged(ctx: &mut Instance, m: Move) -> bool {
+    if !ctx.play(m) {
+        return false;
+    }
+    match m {
+        Move::Step(p, pos) => ctx.log_move(p, pos),
+        Move::Call => ctx.log_call(),
+    }
+    true
+}
+
+/// Lets the computer opponent move if it is its turn
+fn computer_reply(ctx: &mut Instance, computer: Option<&Computer>, cfg: &PlayerConfig, prot: bool) {
+    let c = match computer {
+        Some(c) => c,
+        None => return,
+    };
+    if ctx.side != c.side || ctx.outcome.is_some() || !ctx.game_set() {
+        return;
+    }
+    match c.choose(ctx, cfg) {
+        Some(m) if play_logged(ctx, m) => {
+            if !prot {
+                println!("computer ({}): {}", c.side, m);
+            } else {
+                println!("move {}", m);
+            }
+        }
+        _ => {
+            if !prot {
+                println!("computer has no move");
+            } else {
+                println!("move none");
+            }
+        }
+    }
+}
+
+/// Formats both clocks, with remaining time in milliseconds for protocol clients
+fn clock_line(ctx: &Instance, prot: bool) -> Option<String> {
+    let clocks = ctx.clocks?;
+    let parts = [Side::White, Side::Orange].map(|side| {
+        let left = clocks.remaining(side, ctx.side);
+        if prot {
+            format!("{} {}", side, left.as_millis())
+        } else {
+            format!("{} {}", side, format_duration(left))
+        }
+    });
+    Some(parts.join(if prot { " " } else { " | " }))
+}
+
+/// Reports a loss on time if the side to move's clock ran out
+fn check_flag(ctx: &mut Instance, prot: bool) {
+    if ctx.flag_fall() {
+        if !prot {
+            println!("{} ran out of time, {} wins", ctx.side, !ctx.side);
+        } else {
+            println!("timeout {}", ctx.side);
+        }
+    }
+}
+
+/// Runs one command against the game of ctx and its computer opponent, which replies once
+/// the command has moved or loaded a position
+pub fn cmd(
+    ctx: &mut Instance,
+    computer: &mut Option<Computer>,
+    settings: &mut Settings,
+    s: &str,
+    prot: bool,
+) {
+    check_flag(ctx, prot);
+    exec(ctx, computer, settings, s, prot);
+    let name = s.split_whitespace().next().unwrap_or_default();
+    if matches!(
+        name,
+        "m" | "move" | "c" | "call" | "l" | "load" | "lf" | "load-file"
+    ) {
+        computer_reply(ctx, computer.as_ref(), &settings.engine, prot);
+    }
+    check_flag(ctx, prot);
+}
+
+fn exec(
+    ctx: &mut Instance,
+    computer: &mut Option<Computer>,
+    settings: &mut Settings,
+    s: &str,
+    prot: bool,
+) {
+    let s = s.split_whitespace().collect::<Vec<&str>>();
     match s[0] {
         "l" | "load" => {
             if s.len() > 1 {
@@ -58,9 +250,8 @@ pub fn cmd(mut ctx: &mut Instance, s: &str, prot: bool) {
             } else {
                 ctx.board = Board::new()
             }
-            Arc::get_mut(&mut ctx.states)
-                .unwrap()
-                .push_str(format!("{} {}\n", ctx.board.encode(), ctx.side).as_str());
+            ctx.outcome = None;
+            ctx.log_board();
         }
         "lf" | "load-file" => {
             if s.len() > 1 {
@@ -138,10 +329,9 @@ pub fn cmd(mut ctx: &mut Instance, s: &str, prot: bool) {
                 println!("{}", pos.unwrap_err());
                 return;
             }
-            ctx.make_move(p, pos.unwrap());
-            Arc::get_mut(&mut ctx.states)
-                .unwrap()
-                .push_str(format!("{} {},", s[1], s[2]).as_str());
+            if ctx.make_move(p, pos.unwrap()) {
+                ctx.log_move(p, pos.unwrap());
+            }
         }
         "w" | "who" => {
             if !prot {
@@ -199,34 +389,583 @@ pub fn cmd(mut ctx: &mut Instance, s: &str, prot: bool) {
                 println!("win: {}", ctx.has_win())
             }
         }
+        "perft" => {
+            let divide = s.len() > 1 && s[1] == "divide";
+            let depth = s
+                .get(if divide { 2 } else { 1 })
+                .and_then(|d| d.parse::<u32>().ok());
+            match depth {
+                Some(d) => perft_cmd(ctx, d, divide, prot),
+                None => {
+                    if !prot {
+                        println!("perft [divide] <depth>");
+                    }
+                }
+            }
+        }
+        "go" => {
+            let mut limits = match parse_limits(&s[1..], &settings.engine.limits) {
+                Ok(l) => l,
+                Err(e) => {
+                    println!("{}", e);
+                    return;
+                }
+            };
+            if let (None, None, Some(clocks)) = (limits.depth, limits.movetime, ctx.clocks) {
+                limits.movetime = Some(search::time_for_move(
+                    clocks.remaining(ctx.side, ctx.side),
+                    clocks.get(ctx.side).increment,
+                ));
+            }
+            let result = search::search(ctx, &limits, |info| {
+                println!(
+                    "info depth {} score {} nodes {} time {} pv {}",
+                    info.depth,
+                    info.score,
+                    info.nodes,
+                    info.elapsed.as_millis(),
+                    search::format_pv(&info.pv)
+                )
+            });
+            match result.best {
+                Some(m) => println!("bestmove {}", m),
+                None => println!("bestmove none"),
+            }
+        }
+        "book" => {
+            match s.get(1) {
+                Some(&"off") => settings.engine.book = None,
+                Some(path) => match book::Book::load(path) {
+                    Ok(b) => settings.engine.book = Some(Arc::new(b)),
+                    Err(e) => {
+                        println!("{}", e);
+                        return;
+                    }
+                },
+                None => {}
+            }
+            let b = match &settings.engine.book {
+                Some(b) => b,
+                None => {
+                    println!("book: off");
+                    return;
+                }
+            };
+            let moves = b.moves(ctx);
+            let total: u32 = moves.iter().map(|m| m.weight).sum();
+            if moves.is_empty() {
+                println!(
+                    "{}",
+                    if prot {
+                        "book none"
+                    } else {
+                        "position not in book"
+                    }
+                );
+            }
+            for m in moves {
+                if prot {
+                    println!("book {} {}", m.mv, m.weight);
+                } else {
+                    println!(
+                        "{}: {} ({}%)",
+                        m.mv,
+                        m.weight,
+                        m.weight as u64 * 100 / total.max(1) as u64
+                    );
+                }
+            }
+        }
+        "analyze" => {
+            let mut lines = 3;
+            let mut depth = search::DEFAULT_DEPTH;
+            let mut args = &s[1..];
+            if let Some(Ok(n)) = args.first().map(|n| n.parse::<usize>()) {
+                lines = n.max(1);
+                args = &args[1..];
+            }
+            match args {
+                [] => {}
+                ["depth", d] if d.parse::<u32>().is_ok() => depth = d.parse().unwrap_or(depth),
+                _ => {
+                    println!("analyze [lines] [depth <n>]");
+                    return;
+                }
+            }
+            let start = Instant::now();
+            let limits = Limits {
+                depth: Some(depth),
+                ..settings.engine.limits.clone()
+            };
+            let ranked = search::rank_moves(ctx, &limits, None);
+            for (i, r) in ranked.iter().take(lines).enumerate() {
+                if !prot {
+                    println!(
+                        "{}. {} ({}): {}",
+                        i + 1,
+                        r.mv,
+                        r.score,
+                        search::format_pv(&r.pv)
+                    );
+                } else {
+                    println!(
+                        "info multipv {} depth {} score {} pv {}",
+                        i + 1,
+                        depth,
+                        r.score,
+                        search::format_pv(&r.pv)
+                    );
+                }
+            }
+            if !prot {
+                println!(
+                    "{} of {} moves at depth {} ({} ms)",
+                    lines.min(ranked.len()),
+                    ranked.len(),
+                    depth,
+                    start.elapsed().as_millis()
+                );
+            }
+        }
+        "probe" => {
+            if let Some(dir) = s.get(1) {
+                settings.tablebase = Some(dir.into());
+            }
+            let dir = match &settings.tablebase {
+                Some(dir) => dir,
+                None => {
+                    println!("probe <tablebase directory>");
+                    return;
+                }
+            };
+            if ctx.outcome.is_some() || ctx.miss_call[&ctx.side] {
+                println!("position not in tablebase");
+                return;
+            }
+            let result = match tablebase::probe_dir(dir, ctx) {
+                Ok(result) => result,
+                Err(e) => {
+                    println!("{}", e);
+                    return;
+                }
+            };
+            match (result, prot) {
+                (TbResult::Win(d), true) => println!("probe win {}", d),
+                (TbResult::Loss(d), true) => println!("probe loss {}", d),
+                (TbResult::Draw, true) => println!("probe draw"),
+                (result, false) => {
+                    println!("{} to move: {}", ctx.side, result);
+                    if !ctx.call[&Side::White] || !ctx.call[&Side::Orange] {
+                        println!("(assumes both sides have called)");
+                    }
+                }
+            }
+        }
+        "solve" => {
+            let max_depth = match s.get(1).map(|d| d.parse::<u32>()) {
+                None => solve::DEFAULT_MAX_DEPTH,
+                Some(Ok(d)) => d,
+                Some(Err(_)) => {
+                    println!("solve [max depth]");
+                    return;
+                }
+            };
+            let start = Instant::now();
+            let result = solve::solve(ctx, max_depth, solve::DEFAULT_MAX_NODES);
+            match (result.proof, prot) {
+                (Proof::Win(line), false) => println!(
+                    "{} wins in {} turns: {}",
+                    ctx.side,
+                    line.len(),
+                    search::format_pv(&line)
+                ),
+                (Proof::Win(line), true) => println!("solve win {}", search::format_pv(&line)),
+                (Proof::NoWin, false) => {
+                    println!("no forced win for {} within {} turns", ctx.side, max_depth)
+                }
+                (Proof::NoWin, true) => println!("solve none"),
+                (Proof::Unknown, false) => {
+                    println!("unknown, gave up after {} nodes", result.nodes)
+                }
+                (Proof::Unknown, true) => println!("solve unknown"),
+            }
+            if !prot {
+                println!(
+                    "nodes: {} ({} ms)",
+                    result.nodes,
+                    start.elapsed().as_millis()
+                );
+            }
+        }
+        "mcts" => {
+            let limits = match parse_mcts_limits(&s[1..]) {
+                Ok(l) => l,
+                Err(e) => {
+                    println!("{}", e);
+                    return;
+                }
+            };
+            let start = Instant::now();
+            let result = mcts::mcts(ctx, &limits, &mut Rng::from_time());
+            for stats in result.moves.iter() {
+                println!(
+                    "info move {} visits {} winrate {:.3}",
+                    stats.mv, stats.visits, stats.win_rate
+                );
+            }
+            println!(
+                "info iterations {} time {} pv {}",
+                result.iterations,
+                start.elapsed().as_millis(),
+                search::format_pv(&result.pv)
+            );
+            match result.best {
+                Some(m) => println!("bestmove {}", m),
+                None => println!("bestmove none"),
+            }
+        }
+        "computer" => {
+            match s.get(1) {
+                Some(&"off") => *computer = None,
+                Some(side) => {
+                    let side = match Side::from_str(side) {
+                        Ok(side) => side,
+                        Err(_) => {
+                            println!("computer <White|Orange|off> [player]");
+                            return;
+                        }
+                    };
+                    let player = s.get(2).unwrap_or(&"lookahead").to_string();
+                    if let Err(e) = player::from_spec(&player, 0, &settings.engine) {
+                        println!("{}", e);
+                        return;
+                    }
+                    *computer = Some(Computer { side, player });
+                }
+                None => {}
+            }
+            match computer {
+                Some(c) => println!("computer: {} ({})", c.side, c.player),
+                None => println!("computer: off"),
+            }
+        }
+        "skill" => {
+            match s.get(1).map(|l| l.parse::<u8>()) {
+                Some(Ok(level)) if level <= player::MAX_SKILL => {
+                    let side = computer.as_ref().map_or(Side::Orange, |c| c.side);
+                    *computer = Some(Computer {
+                        side,
+                        player: format!("skill:{}", level),
+                    });
+                }
+                Some(_) => {
+                    println!("skill <0-20>");
+                    return;
+                }
+                None => {}
+            }
+            match computer {
+                Some(c) => println!("computer: {} ({})", c.side, c.player),
+                None => println!("computer: off"),
+            }
+        }
+        "clock" => {
+            match s.get(1).map(|b| (*b, b.parse::<f64>())) {
+                Some(("off", _)) => ctx.clocks = None,
+                Some((_, Ok(base))) if base >= 0.0 => {
+                    let increment = s.get(2).and_then(|i| i.parse::<f64>().ok()).unwrap_or(0.0);
+                    let mut clocks = Clocks::new(
+                        Duration::from_secs_f64(base),
+                        Duration::from_secs_f64(increment.max(0.0)),
+                    );
+                    if ctx.outcome.is_none() {
+                        clocks.start();
+                    }
+                    ctx.clocks = Some(clocks);
+                }
+                Some(_) => {
+                    println!("clock [off | <base seconds> [increment seconds]]");
+                    return;
+                }
+                None => {}
+            }
+            match clock_line(ctx, prot) {
+                Some(line) => println!("{}", line),
+                None => println!("clock: off"),
+            }
+        }
+        "time" => {
+            let side = match s.get(1).map(|side| Side::from_str(side)) {
+                Some(Ok(side)) => side,
+                _ => {
+                    println!("time <White|Orange> [milliseconds]");
+                    return;
+                }
+            };
+            let to_move = ctx.side;
+            let clocks = match ctx.clocks.as_mut() {
+                Some(c) => c,
+                None => {
+                    println!("clock: off");
+                    return;
+                }
+            };
+            if let Some(ms) = s.get(2).and_then(|ms| ms.parse::<u64>().ok()) {
+                clocks.set_remaining(side, to_move, Duration::from_millis(ms));
+            }
+            println!("{}", clocks.remaining(side, to_move).as_millis());
+        }
+        "weights" => {
+            if s.len() > 1 {
+                match eval::Weights::load(s[1]) {
+                    Ok(w) => settings.engine.limits.weights = w,
+                    Err(e) => {
+                        println!("{}", e);
+                        return;
+                    }
+                }
+            }
+            print!("{}", settings.engine.limits.weights);
+        }
+        #[cfg(feature = "nnue")]
+        "nnue" => {
+            match s.get(1) {
+                Some(&"off") => settings.engine.limits.network = None,
+                Some(path) => match nnue::Network::load(path) {
+                    Ok(net) => settings.engine.limits.network = Some(Arc::new(net)),
+                    Err(e) => {
+                        println!("{}", e);
+                        return;
+                    }
+                },
+                None => {}
+            }
+            match &settings.engine.limits.network {
+                Some(net) => println!("nnue: {} hidden", net.hidden),
+                None => println!("nnue: off"),
+            }
+        }
+        "eval" => {
+            let weights = &settings.engine.limits.weights;
+            for (name, value) in eval::breakdown(ctx, weights) {
+                println!("{}: {}", name, value);
+            }
+            if !prot {
+                println!("total ({}): {}", ctx.side, eval::evaluate(ctx, weights));
+            } else {
+                println!("total: {}", eval::evaluate(ctx, weights));
+            }
+        }
         "ping" => println!("ok"),
         "set" => println!("{}", ctx.game_set()),
         "turn" | "t" => println!("{}", ctx.side),
-        "call" | "c" => ctx.call_win(),
+        "call" | "c" => {
+            ctx.call_win();
+            ctx.log_call();
+        }
         "miss-call" | "mc" => println!("{}", ctx.has_miss_call()),
+        "can-call" | "cc" => println!("{}", ctx.can_call()),
 
-        _ => return,
+        _ => {}
     }
 }
-pub fn blank_instance() -> Instance {
-    Instance {
-        board: Board::blank(),
-        side: Side::White,
-        call: HashMap::from([(Side::White, false), (Side::Orange, false)]),
-        miss_call: HashMap::from([(Side::White, false), (Side::Orange, false)]),
-        last_move: HashMap::from([(Side::White, Piece::None), (Side::Orange, Piece::None)]),
-        states: Arc::new(String::from("")),
+
+/// Where puzzle mode keeps the success rate between sessions
+pub const PUZZLE_STATS_FILE: &str = "puzzle_stats.txt";
+
+/// Puzzle mode: the user plays the solving side and the opponent's forced replies come
+/// from the solution line
+struct Trainer {
+    puzzles: Vec<Puzzle>,
+    index: usize,
+    ctx: Instance,
+    /// moves still to come, starting with the user's
+    line: Vec<Move>,
+    stats: puzzle::Stats,
+}
+
+impl Trainer {
+    fn start(path: &str) -> Result<Self, &'static str> {
+        let puzzles = puzzle::load(path)?;
+        if puzzles.is_empty() {
+            return Err("no puzzles in file");
+        }
+        let mut t = Trainer {
+            puzzles,
+            index: 0,
+            ctx: Instance::blank(),
+            line: vec![],
+            stats: puzzle::Stats::load(PUZZLE_STATS_FILE)?,
+        };
+        t.present();
+        Ok(t)
+    }
+
+    fn prompt(&self) -> String {
+        format!("(puzzle {}/{})", self.index + 1, self.puzzles.len())
+    }
+
+    fn present(&mut self) {
+        let p = &self.puzzles[self.index];
+        self.ctx = p.position.scratch();
+        self.line = p.solution.clone();
+        let goal = if p.is_win() { "win" } else { "hold" };
+        println!(
+            "puzzle {} of {}: {} to move and {} ({})",
+            self.index + 1,
+            self.puzzles.len(),
+            self.ctx.side,
+            goal,
+            p.theme
+        );
+        for side in [Side::White, Side::Orange] {
+            if self.ctx.call[&side] {
+                println!("{} has called", side);
+            }
+        }
+        self.ctx.board.print_board();
+    }
+
+    /// Records the attempt and moves on. Returns false once every puzzle has been tried.
+    fn finish(&mut self, solved: bool) -> bool {
+        if solved {
+            println!("solved!");
+        } else {
+            println!(
+                "solution: {}",
+                search::format_pv(&self.puzzles[self.index].solution)
+            );
+        }
+        self.stats.add(solved);
+        if let Err(e) = self.stats.save(PUZZLE_STATS_FILE) {
+            println!("{}", e);
+        }
+        println!("{}", self.stats);
+        self.next()
+    }
+
+    /// Moves on to the next puzzle. Returns false if there is none.
+    fn next(&mut self) -> bool {
+        self.index += 1;
+        if self.index == self.puzzles.len() {
+            println!("no puzzles left");
+            return false;
+        }
+        self.present();
+        true
+    }
+
+    /// Returns the forced line on from ctx if its side to move still loses within
+    /// turns, which lets a different win than the stored one count
+    fn still_lost(ctx: &Instance, winner: Side, turns: usize) -> Option<Vec<Move>> {
+        match ctx.outcome {
+            Some(Outcome::Win(s, _)) if s == winner => return Some(vec![]),
+            Some(_) => return None,
+            None if turns == 0 => return None,
+            None => {}
+        }
+        let limits = Limits {
+            depth: Some(turns as u32),
+            ..Limits::default()
+        };
+        let best = search::rank_moves(ctx, &limits, None).into_iter().next()?;
+        (best.score < 0 && search::is_win_score(best.score)).then_some(best.pv)
+    }
+
+    /// Handles a line of input. Returns false to leave puzzle mode.
+    fn input(&mut self, s: &str) -> bool {
+        match s {
+            "quit" | "q" => return false,
+            "b" | "board" => {
+                self.ctx.board.print_board();
+                return true;
+            }
+            "skip" => return self.finish(false),
+            _ => {}
+        }
+        let m = match Move::decode(s) {
+            Ok(m) => m,
+            Err(e) => {
+                println!("{}", e);
+                return true;
+            }
+        };
+        if !self.ctx.legal_moves().contains(&m) {
+            println!("illegal move");
+            return true;
+        }
+        let mut after = self.ctx.clone();
+        after.play(m);
+        if self.line.first() == Some(&m) {
+            self.line.remove(0);
+        } else {
+            let winner = self.ctx.side;
+            let line = match self.puzzles[self.index].is_win() {
+                true => Trainer::still_lost(&after, winner, self.line.len() - 1),
+                false => None,
+            };
+            match line {
+                Some(line) => self.line = line,
+                None => {
+                    println!("{} is not it", m);
+                    return self.finish(false);
+                }
+            }
+        }
+        self.ctx = after;
+        if let Some(reply) = self.line.first().copied() {
+            self.line.remove(0);
+            if !self.ctx.play(reply) {
+                // the line breaks off after moves the user got right, so they count
+                println!(
+                    "puzzle is broken, its solution has an illegal reply {}",
+                    reply
+                );
+                return self.finish(true);
+            }
+            println!("opponent: {}", reply);
+            self.ctx.board.print_board();
+        }
+        if self.line.is_empty() {
+            return self.finish(true);
+        }
+        true
     }
 }
-pub fn run() {
+
+pub fn blank_instance() -> Instance {
+    Instance::blank()
+}
+/// Runs the interactive REPL, with computer playing against the user if given
+pub fn run(mut computer: Option<Computer>) {
     let conf: &mut Instance = &mut blank_instance();
+    let mut settings = Settings::default();
+    let mut trainer: Option<Trainer> = None;
     let mut e = DefaultEditor::new().expect("Could not open repl.");
     e.load_history("history.txt").err();
     loop {
-        let res = e.readline("(gtc)");
-        match res.as_ref().unwrap().trim() {
-            "quit" => break,
-            _ => cmd(conf, &res.as_ref().unwrap(), false),
+        let prompt = match (&trainer, clock_line(conf, false)) {
+            (Some(t), _) => t.prompt(),
+            (None, Some(clocks)) => format!("(gtc {})", clocks),
+            (None, None) => "(gtc)".to_string(),
+        };
+        let res = e.readline(&prompt);
+        let line = res.as_ref().unwrap().trim();
+        if let Some(t) = &mut trainer {
+            // puzzle answers stay out of the command history
+            if !t.input(line) {
+                trainer = None;
+            }
+            continue;
+        }
+        match line.split_whitespace().collect::<Vec<&str>>().as_slice() {
+            ["quit"] => break,
+            ["puzzle", path] => match Trainer::start(path) {
+                Ok(t) => trainer = Some(t),
+                Err(e) => println!("{}", e),
+            },
+            ["puzzle", ..] => println!("puzzle <file>"),
+            _ => cmd(conf, &mut computer, &mut settings, line, false),
         }
         e.add_history_entry(res.unwrap().as_str())
             .expect("Bad history");
@@ -0,0 +1,61 @@
+/*!
+Small deterministic pseudo random number generator (xorshift64*).
+
+Good enough for random positions, playouts and opening randomization. Not for anything
+that needs to be unpredictable.
+*/
+
+#[derive(Clone, Copy, Debug)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    /// Returns generator for seed. A zero seed is replaced since xorshift would get stuck.
+    pub fn new(seed: u64) -> Self {
+        Rng {
+            state: if seed == 0 {
+                0x9E37_79B9_7F4A_7C15
+            } else {
+                seed
+            },
+        }
+    }
+
+    /// Returns generator seeded from the system clock
+    #[cfg(feature = "std")]
+    pub fn from_time() -> Self {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0);
+        Rng::new(nanos)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let mut x = self.state;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.state = x;
+        x.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Returns number in `0..n`. Returns 0 if n is 0.
+    pub fn below(&mut self, n: usize) -> usize {
+        if n == 0 {
+            return 0;
+        }
+        (self.next_u64() % n as u64) as usize
+    }
+
+    /// Returns float in `0.0..1.0`
+    pub fn unit(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Returns true with probability p
+    pub fn chance(&mut self, p: f64) -> bool {
+        self.unit() < p
+    }
+}
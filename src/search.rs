@@ -0,0 +1,309 @@
+/*!
+Negamax alpha-beta search with iterative deepening over all legal turns.
+
+Leaves are scored with the network in [`Limits`] when built with the `nnue` feature and one
+is given, otherwise with the handcrafted evaluation and the weights in [`Limits`]. Scores
+are from the side to move's point of view. A won game scores [`WIN`] minus the number of
+turns it takes, so faster wins are preferred and slower losses resisted.
+*/
+
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "nnue")]
+use std::sync::Arc;
+
+#[cfg(feature = "nnue")]
+use crate::nnue::{AccumulatorStack, Network};
+
+use crate::{
+    eval::{evaluate, Weights},
+    game::{Instance, Outcome},
+    movegen::Move,
+    piece::Piece,
+};
+
+pub const WIN: i32 = 100_000;
+pub const MAX_DEPTH: u32 = 64;
+/// Depth used when neither a depth nor a move time is given
+pub const DEFAULT_DEPTH: u32 = 4;
+
+/// How far to search and what to score leaves with
+#[derive(Clone, Debug, Default)]
+pub struct Limits {
+    pub depth: Option<u32>,
+    pub movetime: Option<Duration>,
+    pub weights: Weights,
+    /// scores leaves instead of `weights` if given
+    #[cfg(feature = "nnue")]
+    pub network: Option<Arc<Network>>,
+}
+
+/// Progress report after each completed iteration
+#[derive(Clone, Debug)]
+pub struct Info {
+    pub depth: u32,
+    pub score: i32,
+    pub nodes: u64,
+    pub elapsed: Duration,
+    pub pv: Vec<Move>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct SearchResult {
+    pub best: Option<Move>,
+    pub score: i32,
+    pub depth: u32,
+    pub nodes: u64,
+    pub pv: Vec<Move>,
+}
+
+/// Returns true if score is a forced win or loss
+pub fn is_win_score(score: i32) -> bool {
+    score.abs() > WIN - MAX_DEPTH as i32 * 2
+}
+
+/// Returns score of a finished game for the side to move
+pub fn outcome_score(ctx: &Instance, outcome: Outcome, ply: u32) -> i32 {
+    match outcome {
+        Outcome::Win(s, _) if s == ctx.side => WIN - ply as i32,
+        Outcome::Win(_, _) => -(WIN - ply as i32),
+        Outcome::Stalemate => 0,
+    }
+}
+
+/// Puts previous best move first, then captures, then quiet steps and calls last
+pub fn order_moves(ctx: &Instance, moves: &mut [Move], first: Option<Move>) {
+    moves.sort_by_key(|m| {
+        if Some(*m) == first {
+            return 0;
+        }
+        match m {
+            Move::Step(_, to) if ctx.piece_at(*to) != Piece::None => 1,
+            Move::Step(_, _) => 2,
+            Move::Call => 3,
+        }
+    });
+}
+
+struct Searcher {
+    /// best move of the previous iteration, searched first at the root
+    root_first: Option<Move>,
+    nodes: u64,
+    deadline: Option<Instant>,
+    stopped: bool,
+    weights: Weights,
+    #[cfg(feature = "nnue")]
+    nnue: Option<AccumulatorStack>,
+}
+
+impl Searcher {
+    #[cfg_attr(not(feature = "nnue"), allow(unused_variables))]
+    fn new(root: &Instance, limits: &Limits, deadline: Option<Instant>) -> Self {
+        Searcher {
+            root_first: None,
+            nodes: 0,
+            deadline,
+            stopped: false,
+            weights: limits.weights,
+            #[cfg(feature = "nnue")]
+            nnue: limits
+                .network
+                .clone()
+                .map(|net| AccumulatorStack::new(net, &root.board)),
+        }
+    }
+
+    fn evaluate(&self, ctx: &Instance, _ply: u32) -> i32 {
+        #[cfg(feature = "nnue")]
+        if let Some(stack) = &self.nnue {
+            return stack.evaluate(_ply as usize, ctx.side);
+        }
+        evaluate(ctx, &self.weights)
+    }
+
+    fn negamax(
+        &mut self,
+        ctx: &Instance,
+        depth: u32,
+        ply: u32,
+        mut alpha: i32,
+        beta: i32,
+        pv: &mut Vec<Move>,
+    ) -> i32 {
+        self.nodes += 1;
+        if self.nodes & 1023 == 0 {
+            if let Some(deadline) = self.deadline {
+                if Instant::now() >= deadline {
+                    self.stopped = true;
+                }
+            }
+        }
+        if self.stopped {
+            return 0;
+        }
+        if let Some(outcome) = ctx.outcome {
+            return outcome_score(ctx, outcome, ply);
+        }
+        if depth == 0 {
+            return self.evaluate(ctx, ply);
+        }
+
+        let mut moves = ctx.legal_moves();
+        if moves.is_empty() {
+            return 0;
+        }
+        let first = if ply == 0 { self.root_first } else { None };
+        order_moves(ctx, &mut moves, first);
+
+        let mut best = -WIN - 1;
+        for m in moves {
+            let mut child = ctx.clone();
+            if !child.play(m) {
+                continue;
+            }
+            #[cfg(feature = "nnue")]
+            if let Some(stack) = &mut self.nnue {
+                stack.update(ply as usize + 1, &ctx.board, &child.board);
+            }
+            let mut child_pv = vec![];
+            let score = -self.negamax(&child, depth - 1, ply + 1, -beta, -alpha, &mut child_pv);
+            if self.stopped {
+                return 0;
+            }
+            if score > best {
+                best = score;
+                if score > alpha {
+                    alpha = score;
+                    pv.clear();
+                    pv.push(m);
+                    pv.extend(child_pv);
+                }
+                if alpha >= beta {
+                    break;
+                }
+            }
+        }
+        best
+    }
+}
+
+/// Searches ctx with iterative deepening until limits are hit. `on_info` is called after
+/// each completed depth.
+pub fn search(ctx: &Instance, limits: &Limits, mut on_info: impl FnMut(&Info)) -> SearchResult {
+    let start = Instant::now();
+    let root = ctx.scratch();
+    let max_depth = match (limits.depth, limits.movetime) {
+        (Some(d), _) => d.clamp(1, MAX_DEPTH),
+        (None, Some(_)) => MAX_DEPTH,
+        (None, None) => DEFAULT_DEPTH,
+    };
+    let mut searcher = Searcher::new(&root, limits, limits.movetime.map(|t| start + t));
+
+    let mut result = SearchResult::default();
+    let mut moves = root.legal_moves();
+    if moves.len() == 1 {
+        result.best = Some(moves[0]);
+        result.pv = moves;
+        return result;
+    }
+
+    for depth in 1..=max_depth {
+        let mut pv = vec![];
+        searcher.root_first = result.best;
+        let score = searcher.negamax(&root, depth, 0, -WIN - 1, WIN + 1, &mut pv);
+        if searcher.stopped {
+            break;
+        }
+        result = SearchResult {
+            best: pv.first().copied(),
+            score,
+            depth,
+            nodes: searcher.nodes,
+            pv,
+        };
+        on_info(&Info {
+            depth,
+            score,
+            nodes: searcher.nodes,
+            elapsed: start.elapsed(),
+            pv: result.pv.clone(),
+        });
+        if is_win_score(score) {
+            break;
+        }
+    }
+
+    if result.best.is_none() {
+        // ran out of time before the first iteration finished
+        order_moves(&root, &mut moves, None);
+        result.best = moves.first().copied();
+    }
+    result.nodes = searcher.nodes;
+    result
+}
+
+/// Returns how long to think with remaining time on the clock and the increment to come
+pub fn time_for_move(remaining: Duration, increment: Duration) -> Duration {
+    let share = remaining / 30 + increment * 3 / 4;
+    share.min(remaining / 2)
+}
+
+/// Root move with its own score, as opposed to the bound alpha-beta gives non-best moves
+#[derive(Clone, Debug)]
+pub struct RankedMove {
+    pub mv: Move,
+    pub score: i32,
+    /// line starting with `mv`
+    pub pv: Vec<Move>,
+}
+
+/// Scores legal moves of ctx with a search to `limits.depth` ([`DEFAULT_DEPTH`] if unset),
+/// best first. The move time isn't used. Moves more than `margin` below the best are left
+/// out, which lets their search stop as soon as that is certain. `None` scores every move
+/// exactly.
+pub fn rank_moves(ctx: &Instance, limits: &Limits, margin: Option<i32>) -> Vec<RankedMove> {
+    let root = ctx.scratch();
+    let depth = limits.depth.unwrap_or(DEFAULT_DEPTH).clamp(1, MAX_DEPTH);
+    let mut searcher = Searcher::new(&root, limits, None);
+    let mut moves = root.legal_moves();
+    order_moves(&root, &mut moves, None);
+
+    let mut ranked: Vec<RankedMove> = vec![];
+    let mut best: Option<i32> = None;
+    for m in moves {
+        let mut child = root.clone();
+        if !child.play(m) {
+            continue;
+        }
+        #[cfg(feature = "nnue")]
+        if let Some(stack) = &mut searcher.nnue {
+            stack.update(1, &root.board, &child.board);
+        }
+        let alpha = match (best, margin) {
+            (Some(b), Some(w)) => b - w - 1,
+            _ => -WIN - 1,
+        };
+        let mut child_pv = vec![];
+        let score = -searcher.negamax(&child, depth - 1, 1, -WIN - 1, -alpha, &mut child_pv);
+        if score <= alpha {
+            continue;
+        }
+        best = Some(best.map_or(score, |b| b.max(score)));
+        let mut pv = vec![m];
+        pv.extend(child_pv);
+        ranked.push(RankedMove { mv: m, score, pv });
+    }
+    if let (Some(b), Some(w)) = (best, margin) {
+        ranked.retain(|r| r.score >= b - w);
+    }
+    ranked.sort_by_key(|r| -r.score);
+    ranked
+}
+
+/// Formats a principal variation in move log notation
+pub fn format_pv(pv: &[Move]) -> String {
+    pv.iter()
+        .map(|m| m.to_string())
+        .collect::<Vec<String>>()
+        .join(", ")
+}
@@ -0,0 +1,145 @@
+/*!
+Engine self-play for generating training data.
+
+Each game starts from [`Instance::start`] with a few uniformly random turns to vary the
+opening, after which both sides play the best move of a fixed depth search, calling as soon
+as a win is one step away. Fixed depth players shuffle back and forth easily, so games are
+cut off on repetition as well as on length. Every position with more than one legal move is
+kept with the mover's search score, random and call turns included, and labelled with the
+result once the game is over.
+*/
+
+use std::collections::HashMap;
+
+use rayon::prelude::*;
+
+use crate::{
+    book,
+    dataset::TrainingRecord,
+    game::{Instance, Outcome},
+    movegen::Move,
+    record::GameRecord,
+    rng::Rng,
+    search::{search, Limits},
+};
+
+#[derive(Clone, Copy, Debug)]
+pub struct SelfPlayConfig {
+    pub depth: u32,
+    /// random turns are drawn from `0..=random_plies`
+    pub random_plies: usize,
+    /// games still going after this many turns are left unfinished
+    pub max_plies: usize,
+    /// games are left unfinished once a position comes up this many times
+    pub repetitions: usize,
+    pub seed: u64,
+}
+
+impl Default for SelfPlayConfig {
+    fn default() -> Self {
+        SelfPlayConfig {
+            depth: 2,
+            random_plies: 8,
+            max_plies: 300,
+            repetitions: 3,
+            seed: 1,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PlayedGame {
+    pub record: GameRecord,
+    /// turns at the start of the record that were played at random
+    pub random_plies: usize,
+    pub positions: Vec<TrainingRecord>,
+    pub outcome: Option<Outcome>,
+}
+
+impl PlayedGame {
+    /// Returns the game from its first searched turn on, without the random opening
+    pub fn searched(&self) -> GameRecord {
+        let random = self.random_plies.min(self.record.moves.len());
+        let mut start = self.record.start.scratch();
+        for m in self.record.moves[..random].iter() {
+            start.play(*m);
+        }
+        GameRecord {
+            start,
+            moves: self.record.moves[random..].to_vec(),
+        }
+    }
+}
+
+/// Plays a single game. Same config and rng state give the same game.
+pub fn play_game(cfg: &SelfPlayConfig, rng: &mut Rng) -> PlayedGame {
+    let start = Instance::start();
+    let mut record = GameRecord {
+        start: start.scratch(),
+        moves: vec![],
+    };
+    let limits = Limits {
+        depth: Some(cfg.depth),
+        ..Limits::default()
+    };
+
+    let mut ctx = start;
+    let mut scored = vec![];
+    let mut seen: HashMap<u64, usize> = HashMap::new();
+    let random_plies = rng.below(cfg.random_plies + 1);
+    while ctx.outcome.is_none() && record.moves.len() < cfg.max_plies {
+        let count = seen.entry(book::hash(&ctx)).or_insert(0);
+        *count += 1;
+        if *count >= cfg.repetitions {
+            break;
+        }
+        let moves = ctx.legal_moves();
+        if moves.is_empty() {
+            break;
+        }
+        // random and call turns are still searched for their score. A lone legal move is
+        // played without searching and has none, as in `dataset::label_games`.
+        let result = search(&ctx, &limits, |_| {});
+        if moves.len() > 1 {
+            scored.push((ctx.scratch(), result.score));
+        }
+        let m = if record.moves.len() < random_plies {
+            moves[rng.below(moves.len())]
+        } else if ctx.can_call() {
+            Move::Call
+        } else {
+            match result.best {
+                Some(m) => m,
+                None => break,
+            }
+        };
+        if !ctx.play(m) {
+            break;
+        }
+        record.moves.push(m);
+    }
+
+    let outcome = ctx.outcome;
+    let positions = scored
+        .iter()
+        .filter_map(|(p, score)| TrainingRecord::new(p, *score, outcome).ok())
+        .collect();
+    PlayedGame {
+        record,
+        random_plies,
+        positions,
+        outcome,
+    }
+}
+
+/// Plays games in parallel. Each game gets its own generator derived from `cfg.seed` and its
+/// number, so results don't depend on the number of threads.
+pub fn play_games(cfg: &SelfPlayConfig, games: usize) -> Vec<PlayedGame> {
+    (0..games)
+        .into_par_iter()
+        .map(|i| {
+            let seed = cfg.seed ^ (i as u64 + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+            play_game(cfg, &mut Rng::new(seed))
+        })
+        .collect()
+}
@@ -0,0 +1,119 @@
+/*!
+Thread-safe handle to a single game for servers with many connections.
+
+The handle owns the [`Instance`] and with it the move log. Every accepted move or call is
+applied and logged under one lock, so the log always matches the board. Turns are played on
+a copy without the move log that replaces the game only once accepted, so a panicking
+observer leaves the game as it was before the turn. Readers get either a closure over the
+locked instance or an owned snapshot.
+*/
+
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::{
+    game::{GameObserver, Instance, Outcome},
+    piece::{Piece, Side},
+    position::Position,
+};
+
+#[derive(Clone, Debug)]
+pub struct GameHandle {
+    inner: Arc<Mutex<Instance>>,
+}
+
+impl GameHandle {
+    /// Takes ownership of instance and its move log
+    pub fn new(ctx: Instance) -> Self {
+        GameHandle {
+            inner: Arc::new(Mutex::new(ctx)),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Instance> {
+        // updates are built on a copy and assigned whole, so a poisoned game is still intact
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Runs f with shared access to the game
+    pub fn read<R>(&self, f: impl FnOnce(&Instance) -> R) -> R {
+        f(&self.lock())
+    }
+
+    /// Returns an owned copy of the game
+    pub fn snapshot(&self) -> Instance {
+        self.lock().clone()
+    }
+
+    pub fn side(&self) -> Side {
+        self.lock().side
+    }
+
+    pub fn outcome(&self) -> Option<Outcome> {
+        self.lock().outcome
+    }
+
+    /// Returns a copy of the move log
+    pub fn states(&self) -> String {
+        self.lock().states.clone()
+    }
+
+    /// Ends the game on time if the side to move is out of it, then checks that side may
+    /// play a turn
+    fn check_turn(ctx: &mut Instance, side: Side) -> Result<(), &'static str> {
+        if ctx.flag_fall() {
+            return Err("out of time");
+        }
+        if ctx.side != side {
+            return Err("not your turn");
+        }
+        if ctx.outcome.is_some() {
+            return Err("game over");
+        }
+        Ok(())
+    }
+
+    /// Returns a copy of ctx to play a turn on. The move log is left behind and only
+    /// handed over once the turn is accepted.
+    fn turn_copy(ctx: &Instance) -> Instance {
+        let mut next = ctx.scratch();
+        next.clocks = ctx.clocks;
+        next.observers = ctx.observers.clone();
+        next
+    }
+
+    /// Replaces the game with next, moving the log over
+    fn commit(ctx: &mut Instance, mut next: Instance) {
+        std::mem::swap(&mut next.states, &mut ctx.states);
+        *ctx = next;
+    }
+
+    /// Plays and logs a move if it is `side`'s turn and the move is legal
+    pub fn submit_move(&self, side: Side, p: Piece, pos: Position) -> Result<(), &'static str> {
+        let mut ctx = self.lock();
+        GameHandle::check_turn(&mut ctx, side)?;
+        let mut next = GameHandle::turn_copy(&ctx);
+        if !next.make_move(p, pos) {
+            return Err("invalid move");
+        }
+        GameHandle::commit(&mut ctx, next);
+        ctx.log_move(p, pos);
+        Ok(())
+    }
+
+    /// Calls a win and logs it if it is `side`'s turn
+    pub fn submit_call(&self, side: Side) -> Result<(), &'static str> {
+        let mut ctx = self.lock();
+        GameHandle::check_turn(&mut ctx, side)?;
+        let mut next = GameHandle::turn_copy(&ctx);
+        next.call_win();
+        GameHandle::commit(&mut ctx, next);
+        ctx.log_call();
+        Ok(())
+    }
+
+    /// Registers observer on the shared game. Observers run while the lock is held and must
+    /// not call back into the handle.
+    pub fn observe(&self, o: Arc<dyn GameObserver>) {
+        self.lock().observe(o);
+    }
+}
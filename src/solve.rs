@@ -0,0 +1,198 @@
+/*!
+Proof-number search for forced wins.
+
+The side to move at the root is the attacker. Its turns are OR nodes (one winning turn is
+enough) and the defender's turns are AND nodes (every reply has to lose). A node is proven
+once the attacker has won, and disproven once the game ends any other way, the defender
+has no turn, or the turn limit is reached without a win. The search always expands the
+most proving leaf until the root is settled or it runs out of nodes.
+*/
+
+use crate::{
+    game::{Instance, Outcome},
+    movegen::Move,
+    piece::Side,
+};
+
+/// Turn limit used when none is given
+pub const DEFAULT_MAX_DEPTH: u32 = 7;
+/// Node limit used when none is given
+pub const DEFAULT_MAX_NODES: usize = 200_000;
+
+const INF: u32 = u32::MAX;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Proof {
+    /// forced win, with the attacker's winning line against the defence it was proven on
+    Win(Vec<Move>),
+    /// no forced win within the turn limit
+    NoWin,
+    /// ran out of nodes before settling
+    Unknown,
+}
+
+#[derive(Clone, Debug)]
+pub struct SolveResult {
+    pub proof: Proof,
+    pub nodes: usize,
+}
+
+struct Node {
+    /// position, dropped once the node is expanded
+    ctx: Option<Instance>,
+    mv: Option<Move>,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    ply: u32,
+    or_node: bool,
+    pn: u32,
+    dn: u32,
+}
+
+struct Solver {
+    attacker: Side,
+    max_depth: u32,
+    nodes: Vec<Node>,
+}
+
+impl Solver {
+    fn leaf(&self, ctx: Instance, mv: Option<Move>, parent: Option<usize>, ply: u32) -> Node {
+        let or_node = ctx.side == self.attacker;
+        let (pn, dn) = match ctx.outcome {
+            Some(Outcome::Win(s, _)) if s == self.attacker => (0, INF),
+            Some(_) => (INF, 0),
+            None if ply >= self.max_depth => (INF, 0),
+            None => (1, 1),
+        };
+        Node {
+            ctx: Some(ctx),
+            mv,
+            parent,
+            children: vec![],
+            ply,
+            or_node,
+            pn,
+            dn,
+        }
+    }
+
+    fn most_proving(&self) -> usize {
+        let mut n = 0;
+        while !self.nodes[n].children.is_empty() {
+            let node = &self.nodes[n];
+            let key = |c: &&usize| match node.or_node {
+                true => self.nodes[**c].pn,
+                false => self.nodes[**c].dn,
+            };
+            n = *node
+                .children
+                .iter()
+                .min_by_key(key)
+                .unwrap_or(&node.children[0]);
+        }
+        n
+    }
+
+    fn expand(&mut self, n: usize) {
+        let ctx = match self.nodes[n].ctx.take() {
+            Some(ctx) => ctx,
+            None => return,
+        };
+        let ply = self.nodes[n].ply;
+        for m in ctx.legal_moves() {
+            let mut child = ctx.clone();
+            if !child.play(m) {
+                continue;
+            }
+            let node = self.leaf(child, Some(m), Some(n), ply + 1);
+            self.nodes.push(node);
+            let c = self.nodes.len() - 1;
+            self.nodes[n].children.push(c);
+        }
+        if self.nodes[n].children.is_empty() {
+            // no turn to play, so no win either
+            self.nodes[n].pn = INF;
+            self.nodes[n].dn = 0;
+        }
+    }
+
+    fn update(&mut self, n: usize) {
+        let node = &self.nodes[n];
+        if node.children.is_empty() {
+            return;
+        }
+        let pns = node.children.iter().map(|c| self.nodes[*c].pn);
+        let dns = node.children.iter().map(|c| self.nodes[*c].dn);
+        let (pn, dn) = if node.or_node {
+            (
+                pns.min().unwrap_or(INF),
+                dns.fold(0u32, u32::saturating_add),
+            )
+        } else {
+            (
+                pns.fold(0u32, u32::saturating_add),
+                dns.min().unwrap_or(INF),
+            )
+        };
+        self.nodes[n].pn = pn;
+        self.nodes[n].dn = dn;
+    }
+
+    fn update_ancestors(&mut self, n: usize) {
+        let mut at = Some(n);
+        while let Some(i) = at {
+            self.update(i);
+            at = self.nodes[i].parent;
+        }
+    }
+
+    /// Follows proven children from the root
+    fn winning_line(&self) -> Vec<Move> {
+        let mut line = vec![];
+        let mut n = 0;
+        loop {
+            let node = &self.nodes[n];
+            let next = node
+                .children
+                .iter()
+                .copied()
+                .find(|c| self.nodes[*c].pn == 0);
+            match next.and_then(|c| Some((c, self.nodes[c].mv?))) {
+                Some((c, m)) => {
+                    line.push(m);
+                    n = c;
+                }
+                None => return line,
+            }
+        }
+    }
+}
+
+/// Tries to prove a forced win for the side to move within `max_depth` turns, expanding
+/// at most `max_nodes` nodes
+pub fn solve(ctx: &Instance, max_depth: u32, max_nodes: usize) -> SolveResult {
+    let root = ctx.scratch();
+    let mut solver = Solver {
+        attacker: root.side,
+        max_depth,
+        nodes: vec![],
+    };
+    let node = solver.leaf(root, None, None, 0);
+    solver.nodes.push(node);
+
+    while solver.nodes[0].pn != 0 && solver.nodes[0].dn != 0 && solver.nodes.len() < max_nodes {
+        let n = solver.most_proving();
+        solver.expand(n);
+        solver.update_ancestors(n);
+    }
+
+    let proof = match (solver.nodes[0].pn, solver.nodes[0].dn) {
+        (0, _) => Proof::Win(solver.winning_line()),
+        (_, 0) => Proof::NoWin,
+        _ => Proof::Unknown,
+    };
+    SolveResult {
+        proof,
+        nodes: solver.nodes.len(),
+    }
+}
@@ -0,0 +1,571 @@
+/*!
+Endgame tablebases for small piece sets, built by retrograde analysis.
+
+A table covers one material, every placement of its pieces with either side to move, and
+assumes both sides have already called so a winning step wins on the spot. Materials are
+named by the white piece letters, `v`, then the orange piece letters, all lower case, e.g.
+`givli` for a white goat and bird against an orange bird and snake.
+
+Generation starts from the positions that are decided in one turn or by a capture into a
+smaller table, then walks [`Instance::unmoves`] back from each decided position in order of
+distance, so every win is found at its shortest and every loss at its longest.
+
+A table file is `GTCB`, the piece count, one byte per piece (`side << 3 | kind`, as in
+[`crate::pack`]), then one byte per position:
+
+- 0: draw, or tiles that can't hold the pieces
+- 1 to 127: the side to move wins in that many turns
+- 128 + n: the side to move loses in n turns
+
+The position index is the side to move (1 for white) plus twice the pieces' normalized
+tiles read as a base 64 number, first piece lowest, so a probe reads a single byte.
+*/
+
+use std::{
+    collections::HashMap,
+    fmt, fs,
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+use rayon::prelude::*;
+
+use crate::{
+    board::Board,
+    game::{Instance, Outcome},
+    movegen::Move,
+    piece::{Piece, Side},
+    position::Normalizable,
+};
+
+/// Most pieces a table can hold
+pub const MAX_PIECES: usize = 4;
+/// Longest distance a table byte can hold
+pub const MAX_DISTANCE: u8 = 127;
+
+const MAGIC: &[u8; 4] = b"GTCB";
+const KINDS: [char; 8] = ['g', 'h', 's', 'i', 't', 'o', 'l', 'm'];
+const NO_WIN: u8 = u8::MAX;
+
+/// Tablebase value for the side to move, distances in turns
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TbResult {
+    Win(u8),
+    Loss(u8),
+    Draw,
+}
+
+impl TbResult {
+    fn from_byte(b: u8) -> Self {
+        match b {
+            0 => TbResult::Draw,
+            1..=MAX_DISTANCE => TbResult::Win(b),
+            _ => TbResult::Loss(b - 128),
+        }
+    }
+
+    fn to_byte(self) -> u8 {
+        match self {
+            TbResult::Draw => 0,
+            TbResult::Win(d) => d,
+            TbResult::Loss(d) => 128 + d,
+        }
+    }
+}
+
+impl fmt::Display for TbResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TbResult::Win(d) => write!(f, "win in {}", d),
+            TbResult::Loss(d) => write!(f, "loss in {}", d),
+            TbResult::Draw => write!(f, "draw"),
+        }
+    }
+}
+
+fn side_bit(s: Side) -> u8 {
+    match s {
+        Side::Orange => 0,
+        Side::White => 1,
+    }
+}
+
+/// Pieces a table covers, white first, each side in piece order
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Material(Vec<Piece>);
+
+impl Material {
+    pub fn new(mut pieces: Vec<Piece>) -> Result<Self, &'static str> {
+        if pieces.is_empty() || pieces.len() > MAX_PIECES {
+            return Err("a table holds one to four pieces");
+        }
+        pieces.sort_by_key(|p| (p.side() != Some(Side::White), p.index()));
+        if pieces.windows(2).any(|w| w[0] == w[1]) {
+            return Err("a piece can only be on the board once");
+        }
+        Ok(Material(pieces))
+    }
+
+    /// Returns material on the board of ctx
+    pub fn of(ctx: &Instance) -> Result<Self, &'static str> {
+        let board = ctx.board;
+        let pieces = board
+            .board_state()
+            .iter_ones()
+            .map(|i| board.piece_from_norm(i as u64))
+            .collect();
+        Material::new(pieces)
+    }
+
+    pub fn pieces(&self) -> &[Piece] {
+        &self.0
+    }
+
+    /// Number of positions in the table, valid or not
+    pub fn size(&self) -> usize {
+        2 << (6 * self.0.len())
+    }
+
+    /// Returns every material left after one piece is captured
+    pub fn captures(&self) -> Vec<Material> {
+        (0..self.0.len())
+            .filter_map(|i| {
+                let mut rest = self.0.clone();
+                rest.remove(i);
+                Material::new(rest).ok()
+            })
+            .collect()
+    }
+
+    /// Returns index of ctx, or `None` if its board holds other pieces
+    pub fn index(&self, ctx: &Instance) -> Option<usize> {
+        let mut board = ctx.board;
+        if board.board_state().count_ones() != self.0.len() {
+            return None;
+        }
+        let mut index = 0;
+        for p in self.0.iter().rev() {
+            if !ctx.has_piece(*p) {
+                return None;
+            }
+            index = index * 64 + board.pos_from_piece(*p).ok()?.normal();
+        }
+        Some(index * 2 + side_bit(ctx.side) as usize)
+    }
+
+    /// Sets up the position at index with both sides called. Returns `None` if two pieces
+    /// share a tile.
+    pub fn position(&self, index: usize) -> Option<Instance> {
+        let mut ctx = Instance::blank();
+        ctx.side = if index & 1 == 1 {
+            Side::White
+        } else {
+            Side::Orange
+        };
+        ctx.call.insert(Side::White, true);
+        ctx.call.insert(Side::Orange, true);
+        let mut board = Board::blank();
+        let mut rest = index >> 1;
+        for p in self.0.iter() {
+            let tile = rest % 64;
+            rest /= 64;
+            if board.board_state()[tile] {
+                return None;
+            }
+            board.put(*p, tile).ok()?;
+        }
+        ctx.board = board;
+        Some(ctx)
+    }
+}
+
+impl std::str::FromStr for Material {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (white, orange) = s.split_once('v').ok_or("material looks like givli")?;
+        let mut pieces = vec![];
+        for (letters, side) in [(white, Side::White), (orange, Side::Orange)] {
+            for c in letters.trim().chars() {
+                let kind = KINDS
+                    .iter()
+                    .position(|k| *k == c.to_ascii_lowercase())
+                    .ok_or("unknown piece letter")?;
+                pieces.push(Piece::from_index(kind as u8, side));
+            }
+        }
+        Material::new(pieces)
+    }
+}
+
+impl fmt::Display for Material {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let letters = |side: Side| {
+            self.0
+                .iter()
+                .filter(|p| p.side() == Some(side))
+                .filter_map(|p| p.index().map(|k| KINDS[k as usize]))
+                .collect::<String>()
+        };
+        write!(f, "{}v{}", letters(Side::White), letters(Side::Orange))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Table {
+    pub material: Material,
+    /// one byte per position, see the module docs
+    pub values: Vec<u8>,
+}
+
+/// Counts of a table's results, for reporting
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TableStats {
+    pub wins: usize,
+    pub losses: usize,
+    pub draws: usize,
+    pub longest: u8,
+}
+
+impl Table {
+    pub fn probe(&self, ctx: &Instance) -> Option<TbResult> {
+        let index = self.material.index(ctx)?;
+        Some(TbResult::from_byte(self.values[index]))
+    }
+
+    pub fn stats(&self) -> TableStats {
+        let mut stats = TableStats::default();
+        for (i, v) in self.values.iter().enumerate() {
+            match TbResult::from_byte(*v) {
+                TbResult::Win(d) => {
+                    stats.wins += 1;
+                    stats.longest = stats.longest.max(d);
+                }
+                TbResult::Loss(d) => {
+                    stats.losses += 1;
+                    stats.longest = stats.longest.max(d);
+                }
+                TbResult::Draw if self.material.position(i).is_some() => stats.draws += 1,
+                TbResult::Draw => {}
+            }
+        }
+        stats
+    }
+
+    fn header(material: &Material) -> Vec<u8> {
+        let mut out = MAGIC.to_vec();
+        out.push(material.0.len() as u8);
+        for p in material.0.iter() {
+            let kind = p.index().unwrap_or_default();
+            out.push(side_bit(p.side().unwrap_or_default()) << 3 | kind);
+        }
+        out
+    }
+
+    /// Reads the material out of a table file header
+    fn read_header(raw: &[u8]) -> Result<Material, &'static str> {
+        if raw.len() < 5 || &raw[0..4] != MAGIC {
+            return Err("not a tablebase file");
+        }
+        let n = raw[4] as usize;
+        if raw.len() < 5 + n {
+            return Err("tablebase file is truncated");
+        }
+        let pieces = raw[5..5 + n]
+            .iter()
+            .map(|b| {
+                let side = if b >> 3 & 1 == 1 {
+                    Side::White
+                } else {
+                    Side::Orange
+                };
+                Piece::from_index(b & 0b111, side)
+            })
+            .collect();
+        Material::new(pieces)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, &'static str> {
+        let raw = fs::read(path).map_err(|_| "Couldn't read tablebase file.")?;
+        let material = Table::read_header(&raw)?;
+        let start = 5 + material.0.len();
+        if raw.len() != start + material.size() {
+            return Err("tablebase file has the wrong size");
+        }
+        Ok(Table {
+            values: raw[start..].to_vec(),
+            material,
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), &'static str> {
+        let mut out = Table::header(&self.material);
+        out.extend_from_slice(&self.values);
+        fs::write(path, out).map_err(|_| "Failed to write tablebase.")
+    }
+}
+
+/// Returns the file a material's table is kept in under dir
+pub fn table_path(dir: &Path, material: &Material) -> PathBuf {
+    dir.join(format!("{}.gtb", material))
+}
+
+/// Looks up the position of ctx in the table files under dir, reading only its byte
+pub fn probe_dir(dir: &Path, ctx: &Instance) -> Result<TbResult, &'static str> {
+    let material = Material::of(ctx)?;
+    let index = material.index(ctx).ok_or("Invalid piece")?;
+    let mut file =
+        File::open(table_path(dir, &material)).map_err(|_| "no table for this material")?;
+    let mut header = vec![0u8; 5 + material.0.len()];
+    file.read_exact(&mut header)
+        .map_err(|_| "tablebase file is truncated")?;
+    if Table::read_header(&header)? != material {
+        return Err("tablebase file holds another material");
+    }
+    let mut value = [0u8];
+    file.seek(SeekFrom::Start((header.len() + index) as u64))
+        .and_then(|_| file.read_exact(&mut value))
+        .map_err(|_| "tablebase file is truncated")?;
+    Ok(TbResult::from_byte(value[0]))
+}
+
+/// Where a turn leads, seen from the side that played it
+enum Step {
+    Won,
+    Drawn,
+    /// position of the same material
+    Quiet,
+    /// the opponent's result after a capture
+    Captured(TbResult),
+}
+
+/// What generation knows about a position so far
+#[derive(Clone, Copy, Debug)]
+struct Node {
+    done: bool,
+    /// turns leading to undecided positions of this table
+    open: u8,
+    best_win: u8,
+    worst_loss: u8,
+    drawn: bool,
+}
+
+const SETTLED: Node = Node {
+    done: true,
+    open: 0,
+    best_win: NO_WIN,
+    worst_loss: 0,
+    drawn: false,
+};
+
+struct Generator<'a> {
+    material: &'a Material,
+    smaller: &'a HashMap<Material, Table>,
+    nodes: Vec<Node>,
+    values: Vec<u8>,
+    buckets: Vec<Vec<u32>>,
+}
+
+impl Generator<'_> {
+    fn step(&self, ctx: &Instance, m: Move) -> Option<Step> {
+        let mut child = ctx.clone();
+        if !child.play(m) {
+            return None;
+        }
+        Some(match child.outcome {
+            Some(Outcome::Win(_, _)) => Step::Won,
+            Some(Outcome::Stalemate) => Step::Drawn,
+            None if self.material.index(&child).is_some() => Step::Quiet,
+            None => {
+                let table = self.smaller.get(&Material::of(&child).ok()?)?;
+                Step::Captured(table.probe(&child)?)
+            }
+        })
+    }
+
+    /// Sorts out every turn of the position at index into what's known and what's left open
+    fn classify(&self, index: usize) -> Node {
+        let ctx = match self.material.position(index) {
+            Some(ctx) => ctx,
+            None => return SETTLED,
+        };
+        let mut node = Node {
+            done: false,
+            ..SETTLED
+        };
+        for m in ctx.legal_moves() {
+            match self.step(&ctx, m) {
+                Some(Step::Won) => node.best_win = 1,
+                Some(Step::Drawn) | Some(Step::Captured(TbResult::Draw)) => node.drawn = true,
+                Some(Step::Quiet) => node.open += 1,
+                Some(Step::Captured(TbResult::Win(d))) => {
+                    node.worst_loss = node.worst_loss.max(d + 1)
+                }
+                Some(Step::Captured(TbResult::Loss(d))) => node.best_win = node.best_win.min(d + 1),
+                None => {}
+            }
+        }
+        if node.best_win == NO_WIN && node.open == 0 && (node.drawn || node.worst_loss == 0) {
+            // a draw, or nothing to play at all
+            node.done = true;
+        }
+        node
+    }
+
+    /// Returns the undecided positions one quiet turn before the position at index
+    fn predecessors(&self, index: usize) -> Vec<usize> {
+        let ctx = match self.material.position(index) {
+            Some(ctx) => ctx,
+            None => return vec![],
+        };
+        ctx.unmoves()
+            .into_iter()
+            .filter_map(|u| {
+                let before = ctx.unmake(u);
+                // only turns that kept the game going lead here
+                match self.step(&before, Move::Step(u.piece, u.to)) {
+                    Some(Step::Quiet) => self.material.index(&before),
+                    _ => None,
+                }
+            })
+            .filter(|p| !self.nodes[*p].done)
+            .collect()
+    }
+
+    fn push(&mut self, distance: u8, index: usize) -> Result<(), &'static str> {
+        if distance > MAX_DISTANCE {
+            return Err("distance too long for a table");
+        }
+        self.buckets[distance as usize].push(index as u32);
+        Ok(())
+    }
+
+    /// Queues every position already known to be won or lost
+    fn seed(&mut self) -> Result<(), &'static str> {
+        self.nodes = (0..self.material.size())
+            .into_par_iter()
+            .map(|i| self.classify(i))
+            .collect();
+        for i in 0..self.nodes.len() {
+            let node = self.nodes[i];
+            if node.done {
+                continue;
+            }
+            if node.best_win != NO_WIN {
+                self.push(node.best_win, i)?;
+            } else if node.open == 0 {
+                self.push(node.worst_loss, i)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Settles positions in order of distance and passes each result back to the positions
+    /// one quiet turn before it
+    fn settle(&mut self) -> Result<(), &'static str> {
+        for d in 1..=MAX_DISTANCE {
+            let mut settled = vec![];
+            for i in std::mem::take(&mut self.buckets[d as usize]) {
+                let i = i as usize;
+                let node = &mut self.nodes[i];
+                if node.done {
+                    continue;
+                }
+                let result = if node.best_win == d {
+                    TbResult::Win(d)
+                } else if node.best_win == NO_WIN
+                    && node.open == 0
+                    && !node.drawn
+                    && node.worst_loss == d
+                {
+                    TbResult::Loss(d)
+                } else {
+                    continue;
+                };
+                node.done = true;
+                self.values[i] = result.to_byte();
+                settled.push((i, result));
+            }
+
+            let before: Vec<(TbResult, Vec<usize>)> = settled
+                .par_iter()
+                .map(|(i, result)| (*result, self.predecessors(*i)))
+                .collect();
+            for (result, predecessors) in before {
+                for p in predecessors {
+                    self.pass_back(p, result)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Updates the position at index with the result of a position its turn leads to
+    fn pass_back(&mut self, index: usize, result: TbResult) -> Result<(), &'static str> {
+        let node = &mut self.nodes[index];
+        match result {
+            TbResult::Loss(d) if d + 1 < node.best_win => {
+                node.best_win = d + 1;
+                self.push(d + 1, index)
+            }
+            TbResult::Win(d) => {
+                node.open -= 1;
+                node.worst_loss = node.worst_loss.max(d + 1);
+                if node.open == 0 && node.best_win == NO_WIN && !node.drawn {
+                    let worst = node.worst_loss;
+                    self.push(worst, index)?;
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Builds the table of material. `smaller` must hold the tables of every material a capture
+/// leads to.
+pub fn generate(
+    material: &Material,
+    smaller: &HashMap<Material, Table>,
+) -> Result<Table, &'static str> {
+    let mut g = Generator {
+        material,
+        smaller,
+        nodes: vec![],
+        values: vec![0; material.size()],
+        buckets: vec![vec![]; MAX_DISTANCE as usize + 2],
+    };
+    g.seed()?;
+    g.settle()?;
+    Ok(Table {
+        material: material.clone(),
+        values: g.values,
+    })
+}
+
+/// Builds the table of material along with every smaller table it needs, loading the ones
+/// already under dir and saving the rest there. Calls `report` after each new table.
+pub fn generate_all(
+    material: &Material,
+    dir: &Path,
+    tables: &mut HashMap<Material, Table>,
+    report: &mut impl FnMut(&Table),
+) -> Result<(), &'static str> {
+    if tables.contains_key(material) {
+        return Ok(());
+    }
+    let path = table_path(dir, material);
+    if let Ok(table) = Table::load(&path) {
+        tables.insert(material.clone(), table);
+        return Ok(());
+    }
+    for smaller in material.captures() {
+        generate_all(&smaller, dir, tables, report)?;
+    }
+    let table = generate(material, tables)?;
+    table.save(&path)?;
+    report(&table);
+    tables.insert(material.clone(), table);
+    Ok(())
+}
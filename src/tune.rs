@@ -0,0 +1,137 @@
+/*!
+Texel-style fitting of evaluation weights to game results.
+
+Every position of a finished game is labelled with the result for white (1 win, 0.5
+stalemate, 0 loss). The evaluation is squashed into an expected result with
+`1 / (1 + 10^(-k * eval / 400))` and weights are nudged one at a time for as long as the
+mean squared prediction error keeps dropping.
+*/
+
+use rayon::prelude::*;
+
+use crate::{
+    eval::{term_diff, Weights},
+    game::Outcome,
+    piece::Side,
+    record::GameRecord,
+};
+
+/// Position terms as white minus orange and the game result for white
+#[derive(Clone, Copy, Debug)]
+pub struct Sample {
+    pub diff: [i32; 7],
+    pub result: f64,
+}
+
+/// Returns result for white of a finished game
+pub fn white_result(outcome: Outcome) -> f64 {
+    match outcome {
+        Outcome::Win(Side::White, _) => 1.0,
+        Outcome::Win(Side::Orange, _) => 0.0,
+        Outcome::Stalemate => 0.5,
+    }
+}
+
+/// Builds samples from every position of finished games. Returns samples and the number of
+/// unfinished games left out.
+pub fn samples(records: &[GameRecord]) -> (Vec<Sample>, usize) {
+    let replays: Vec<Vec<Sample>> = records
+        .par_iter()
+        .map(|r| {
+            let positions = r.replay();
+            let outcome = match positions.last().and_then(|p| p.outcome) {
+                Some(o) => o,
+                None => return vec![],
+            };
+            positions
+                .iter()
+                .filter(|p| p.outcome.is_none())
+                .map(|p| Sample {
+                    diff: term_diff(p, Side::White),
+                    result: white_result(outcome),
+                })
+                .collect()
+        })
+        .collect();
+    let unfinished = replays.iter().filter(|s| s.is_empty()).count();
+    (replays.into_iter().flatten().collect(), unfinished)
+}
+
+fn predict(s: &Sample, w: &[f64; 7], k: f64) -> f64 {
+    let eval: f64 = w.iter().zip(s.diff).map(|(w, d)| w * d as f64).sum();
+    1.0 / (1.0 + 10f64.powf(-k * eval / 400.0))
+}
+
+/// Mean squared error between predicted and actual results
+pub fn error(samples: &[Sample], w: &Weights, k: f64) -> f64 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    let v = w.values();
+    let total: f64 = samples
+        .par_iter()
+        .map(|s| (s.result - predict(s, &v, k)).powi(2))
+        .sum();
+    total / samples.len() as f64
+}
+
+/// Finds scaling constant k that best fits the current weights
+pub fn fit_k(samples: &[Sample], w: &Weights) -> f64 {
+    let mut best = (1.0, error(samples, w, 1.0));
+    let mut step = 0.5;
+    while step > 0.001 {
+        let mut improved = false;
+        for k in [best.0 - step, best.0 + step] {
+            if k <= 0.0 {
+                continue;
+            }
+            let e = error(samples, w, k);
+            if e < best.1 {
+                best = (k, e);
+                improved = true;
+            }
+        }
+        if !improved {
+            step /= 2.0;
+        }
+    }
+    best.0
+}
+
+/// Runs local search over the weights for at most `passes` passes. `on_pass` gets the pass
+/// number and error after each pass.
+pub fn tune(
+    samples: &[Sample],
+    start: Weights,
+    k: f64,
+    passes: usize,
+    mut on_pass: impl FnMut(usize, f64),
+) -> Weights {
+    let mut v = start.values();
+    let mut best = error(samples, &start, k);
+    let mut step = 8.0;
+    for pass in 1..=passes {
+        let mut improved = false;
+        for i in 0..v.len() {
+            for delta in [step, -step] {
+                let mut trial = v;
+                trial[i] += delta;
+                let e = error(samples, &Weights::from_values(trial), k);
+                if e < best {
+                    best = e;
+                    v = trial;
+                    improved = true;
+                    break;
+                }
+            }
+        }
+        on_pass(pass, best);
+        if !improved {
+            if step <= 0.25 {
+                break;
+            }
+            step /= 2.0;
+        }
+    }
+    Weights::from_values(v)
+}
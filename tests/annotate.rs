@@ -0,0 +1,40 @@
+use gtc::{
+    annotate::{annotate, Mark, BLUNDER},
+    movegen::Move,
+    record::GameRecord,
+};
+
+/// White's mantis shrimp on f4 can take the orange tiger on g4
+const TIGER_HANGS: &str = "I1g5/2s5/i7/8/8/2Lm4/3T4/4t White";
+
+#[test]
+fn marks_a_blunder_with_the_better_move() {
+    let record = GameRecord::parse(&format!("{}\ng a4,", TIGER_HANGS)).unwrap();
+    let notes = annotate(&record, 2);
+    assert_eq!(notes.len(), 1);
+    let note = &notes[0];
+    assert_eq!(note.mark, Some(Mark::Blunder));
+    assert!(note.loss >= BLUNDER);
+    assert!(!note.missed_call);
+    let best = note.best.as_ref().unwrap();
+    assert_eq!(best.mv, Move::decode("m g4").unwrap());
+    assert_eq!(note.loss, note.before - note.after);
+}
+
+#[test]
+fn best_move_is_not_marked() {
+    let record = GameRecord::parse(&format!("{}\nm g4,", TIGER_HANGS)).unwrap();
+    let notes = annotate(&record, 2);
+    assert_eq!(notes[0].mark, None);
+    assert_eq!(notes[0].loss, 0);
+    assert!(notes[0].best.is_none());
+}
+
+#[test]
+fn rejected_moves_are_skipped_unmarked() {
+    let record = GameRecord::parse(&format!("{}\nG a4,g a4,", TIGER_HANGS)).unwrap();
+    let notes = annotate(&record, 2);
+    assert!(!notes[0].legal);
+    assert_eq!(notes[0].mark, None);
+    assert_eq!(notes[1].mark, Some(Mark::Blunder));
+}
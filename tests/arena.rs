@@ -0,0 +1,66 @@
+use gtc::arena::{elo, expected_score, Sprt, SprtResult, Wdl};
+
+fn close(a: f64, b: f64) -> bool {
+    (a - b).abs() < 1e-6
+}
+
+fn wdl(wins: usize, draws: usize, losses: usize) -> Wdl {
+    Wdl {
+        wins,
+        draws,
+        losses,
+    }
+}
+
+#[test]
+fn elo_of_known_scores() {
+    assert!(close(elo(0.5), 0.0));
+    assert!(close(elo(0.75), 190.848501888));
+    assert!(close(elo(0.25), -190.848501888));
+    assert!(close(expected_score(190.848501888), 0.75));
+    assert!(close(expected_score(0.0), 0.5));
+}
+
+#[test]
+fn wdl_elo_and_interval() {
+    let (e, margin) = wdl(30, 40, 30).elo();
+    assert!(close(e, 0.0));
+    assert!(close(margin, 53.158971905));
+
+    let (e, margin) = wdl(6, 0, 2).elo();
+    assert!(close(e, 190.848501888));
+    assert!(close(margin, 617.365064846));
+}
+
+#[test]
+fn perfect_score_stays_finite() {
+    // counted as half a game short of perfect
+    let (e, margin) = wdl(10, 0, 0).elo();
+    assert!(close(e, 511.501440381));
+    assert!(close(margin, 471.164118484));
+    assert!(wdl(0, 0, 10).elo().0.is_finite());
+}
+
+#[test]
+fn sprt_decides_at_the_bounds() {
+    let sprt = Sprt {
+        elo0: 0.0,
+        elo1: 10.0,
+        alpha: 0.05,
+        beta: 0.05,
+    };
+    let (lower, upper) = sprt.bounds();
+    assert!(close(lower, -2.944438979));
+    assert!(close(upper, 2.944438979));
+
+    assert_eq!(sprt.test(&Wdl::default()), (0.0, SprtResult::Continue));
+    let (llr, result) = sprt.test(&wdl(300, 400, 100));
+    assert!(close(llr, 12.397001045));
+    assert_eq!(result, SprtResult::H1);
+    let (llr, result) = sprt.test(&wdl(100, 400, 300));
+    assert!(close(llr, -13.910992858));
+    assert_eq!(result, SprtResult::H0);
+    let (llr, result) = sprt.test(&wdl(52, 100, 48));
+    assert!(close(llr, 0.064653815));
+    assert_eq!(result, SprtResult::Continue);
+}
@@ -0,0 +1,32 @@
+use gtc::{batch, board::Board, game::WinReason, piece::Side};
+
+#[test]
+fn checks_every_position_in_order() {
+    let lines: Vec<String> = [
+        "# start position, then a passive win for white",
+        &Board::new().encode(),
+        "",
+        "g7/8/8/3t4/8/8/8/7O White",
+        "g7/8/8/3t4/8/8/8/7O Purple",
+        "not a board",
+    ]
+    .iter()
+    .map(|l| l.to_string())
+    .collect();
+    let results = batch::check_all(&lines);
+    let numbers: Vec<usize> = results.iter().map(|(n, _)| *n).collect();
+    assert_eq!(numbers, vec![2, 4, 5, 6]);
+
+    let start = results[0].1.as_ref().unwrap();
+    assert_eq!(start.side, Side::White);
+    assert_eq!(start.moves, 17);
+    assert_eq!(start.win, None);
+
+    let won = results[1].1.as_ref().unwrap();
+    assert_eq!(won.side, Side::White);
+    assert_eq!(won.win, Some(WinReason::PassiveElimination));
+    assert!(!won.has_win);
+
+    assert_eq!(results[2].1.as_ref().err(), Some(&"invalid side"));
+    assert!(results[3].1.is_err());
+}
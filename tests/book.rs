@@ -0,0 +1,114 @@
+use gtc::{
+    book::{self, Book, BookMove},
+    game::Instance,
+    movegen::Move,
+    piece::Side,
+    record::GameRecord,
+};
+
+fn mv(s: &str) -> Move {
+    Move::decode(s).unwrap()
+}
+
+#[test]
+fn hash_is_stable() {
+    // books are shared between builds, so the keys must never change
+    assert_eq!(book::hash(&Instance::start()), 0xe300_a520_e47a_fafa);
+    assert_eq!(
+        book::hash(&Instance::start()),
+        book::hash(&Instance::start().scratch())
+    );
+}
+
+#[test]
+fn hash_covers_side_and_calls() {
+    let ctx = Instance::start();
+    let mut orange = Instance::start();
+    orange.side = Side::Orange;
+    let mut called = Instance::start();
+    called.call.insert(Side::White, true);
+    let mut missed = Instance::start();
+    missed.miss_call.insert(Side::White, true);
+    let hashes = [ctx, orange, called, missed].map(|c| book::hash(&c));
+    for (i, a) in hashes.iter().enumerate() {
+        for b in hashes[i + 1..].iter() {
+            assert_ne!(a, b);
+        }
+    }
+}
+
+#[test]
+fn bytes_round_trip() {
+    let mut b = Book::new();
+    let ctx = Instance::start();
+    b.add(&ctx, mv("i b8"), 3);
+    b.add(&ctx, mv("c"), 1);
+    b.add(&ctx, mv("i b8"), 2);
+    let mut next = Instance::start();
+    next.play(mv("i b8"));
+    b.add(&next, mv("I g8"), 7);
+
+    let bytes = b.to_bytes();
+    assert_eq!(&bytes[0..4], b"GTCO");
+    assert_eq!(bytes.len(), 8 + 3 * 14);
+    let parsed = Book::parse(&bytes).unwrap();
+    assert_eq!(parsed.len(), 2);
+    assert_eq!(parsed.to_bytes(), bytes);
+    assert_eq!(
+        parsed.moves(&ctx),
+        vec![
+            BookMove {
+                mv: mv("i b8"),
+                weight: 5
+            },
+            BookMove {
+                mv: Move::Call,
+                weight: 1
+            },
+        ]
+    );
+}
+
+#[test]
+fn bad_files_are_errors() {
+    let bytes = Book::new().to_bytes();
+    assert!(Book::parse(&bytes).unwrap().is_empty());
+    assert!(Book::parse(b"GTCB\0\0\0\0").is_err());
+    let mut truncated = {
+        let mut b = Book::new();
+        b.add(&Instance::start(), mv("i b8"), 1);
+        b.to_bytes()
+    };
+    truncated.pop();
+    assert!(Book::parse(&truncated).is_err());
+}
+
+#[test]
+fn unfinished_games_weigh_one() {
+    let record = GameRecord {
+        start: Instance::start(),
+        moves: vec![mv("i b8"), mv("I g8"), mv("c")],
+    };
+    let mut b = Book::new();
+    b.add_game(&record, 2);
+    assert_eq!(b.len(), 2);
+    assert_eq!(b.moves(&Instance::start())[0].weight, 1);
+}
+
+#[test]
+fn rejected_moves_leave_the_rest_in_place() {
+    // the second "i b8" is played out of turn and skipped
+    let record = GameRecord {
+        start: Instance::start(),
+        moves: vec![mv("i b8"), mv("i b8"), mv("I g8"), mv("i c8")],
+    };
+    let mut b = Book::new();
+    b.add_game(&record, 4);
+    assert_eq!(b.len(), 3);
+
+    let mut ctx = Instance::start();
+    for m in ["i b8", "I g8", "i c8"] {
+        assert_eq!(b.moves(&ctx)[0].mv, mv(m), "{}", m);
+        ctx.play(mv(m));
+    }
+}
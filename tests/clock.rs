@@ -0,0 +1,63 @@
+use std::{thread, time::Duration};
+
+use gtc::{
+    clock::{format_duration, Clocks},
+    game::{Instance, Outcome, WinReason},
+    movegen::Move,
+    piece::Side,
+};
+
+fn ms(n: u64) -> Duration {
+    Duration::from_millis(n)
+}
+
+#[test]
+fn clock_flags_once_its_time_runs_out() {
+    let mut clocks = Clocks::new(ms(30), Duration::ZERO);
+    clocks.start();
+    assert!(!clocks.flagged(Side::White));
+    thread::sleep(ms(60));
+    assert!(clocks.flagged(Side::White));
+    assert_eq!(clocks.remaining(Side::White, Side::White), Duration::ZERO);
+    // only the side to move's clock runs
+    assert_eq!(clocks.remaining(Side::Orange, Side::White), ms(30));
+}
+
+#[test]
+fn stopped_clocks_dont_run() {
+    let clocks = Clocks::new(ms(30), Duration::ZERO);
+    thread::sleep(ms(40));
+    assert!(!clocks.flagged(Side::White));
+    assert_eq!(clocks.remaining(Side::White, Side::White), ms(30));
+}
+
+#[test]
+fn increment_is_added_after_the_turn() {
+    let mut clocks = Clocks::new(Duration::from_secs(1), ms(500));
+    clocks.start();
+    clocks.end_turn(Side::White);
+    let white = clocks.remaining(Side::White, Side::Orange);
+    assert!(white > ms(1400) && white <= ms(1500), "{:?}", white);
+    assert!(clocks.is_running());
+}
+
+#[test]
+fn flagged_side_loses_on_time() {
+    let mut ctx = Instance::start();
+    let mut clocks = Clocks::new(ms(30), Duration::ZERO);
+    clocks.start();
+    ctx.clocks = Some(clocks);
+    thread::sleep(ms(60));
+    assert!(!ctx.play(Move::decode("i b8").unwrap()));
+    assert_eq!(
+        ctx.outcome,
+        Some(Outcome::Win(Side::Orange, WinReason::Timeout))
+    );
+    assert!(!ctx.clocks.unwrap().is_running());
+}
+
+#[test]
+fn durations_format_as_minutes_seconds_tenths() {
+    assert_eq!(format_duration(ms(61_540)), "1:01.5");
+    assert_eq!(format_duration(ms(900)), "0:00.9");
+}
@@ -0,0 +1,66 @@
+use gtc::{
+    dataset::{RecordReader, RecordWriter, TrainingRecord, RECORD_SIZE},
+    game::{Instance, Outcome, WinReason},
+    piece::Side,
+};
+
+#[test]
+fn bytes_round_trip_every_result() {
+    let results = [
+        None,
+        Some(Outcome::Stalemate),
+        Some(Outcome::Win(Side::White, WinReason::EdgeAlignment)),
+        Some(Outcome::Win(Side::White, WinReason::PassiveElimination)),
+        Some(Outcome::Win(Side::Orange, WinReason::EdgeAlignment)),
+        Some(Outcome::Win(Side::Orange, WinReason::PassiveElimination)),
+        Some(Outcome::Win(Side::White, WinReason::Timeout)),
+        Some(Outcome::Win(Side::Orange, WinReason::Timeout)),
+    ];
+    for (i, result) in results.into_iter().enumerate() {
+        let r = TrainingRecord::new(&Instance::start(), -37, result).unwrap();
+        let bytes = r.to_bytes();
+        assert_eq!(bytes[RECORD_SIZE - 1], i as u8);
+        assert_eq!(TrainingRecord::from_bytes(&bytes), Ok(r));
+    }
+}
+
+#[test]
+fn score_is_clamped_to_i16() {
+    let high = TrainingRecord::new(&Instance::start(), 1_000_000, None).unwrap();
+    let low = TrainingRecord::new(&Instance::start(), -1_000_000, None).unwrap();
+    assert_eq!(high.score, i16::MAX);
+    assert_eq!(low.score, -i16::MAX);
+}
+
+#[test]
+fn invalid_result_byte_is_an_error() {
+    let mut bytes = TrainingRecord::new(&Instance::start(), 0, None)
+        .unwrap()
+        .to_bytes();
+    bytes[RECORD_SIZE - 1] = 8;
+    assert!(TrainingRecord::from_bytes(&bytes).is_err());
+}
+
+#[test]
+fn stream_round_trip() {
+    let records = [
+        TrainingRecord::new(&Instance::start(), 12, None).unwrap(),
+        TrainingRecord::new(&Instance::start(), -5, Some(Outcome::Stalemate)).unwrap(),
+    ];
+    let mut out = vec![];
+    let mut writer = RecordWriter::new(&mut out);
+    for r in records.iter() {
+        writer.write(r).unwrap();
+    }
+    writer.flush().unwrap();
+    assert_eq!(out.len(), 2 * RECORD_SIZE);
+
+    let read: Vec<TrainingRecord> = RecordReader::new(out.as_slice())
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(read, records);
+    assert!(RecordReader::new(&out[..RECORD_SIZE + 1])
+        .nth(1)
+        .unwrap()
+        .is_err());
+}
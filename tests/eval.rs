@@ -0,0 +1,75 @@
+use gtc::{
+    board::Board,
+    eval::{breakdown, evaluate, terms, Weights, NAMES},
+    game::Instance,
+    piece::Side,
+};
+
+#[test]
+fn format_parse_round_trip() {
+    let w = Weights::from_values([90.5, 61.0, 14.25, 4.0, 22.0, 2.5, 30.125]);
+    let text = w.to_string();
+    assert_eq!(text.lines().count(), NAMES.len());
+    assert!(text.starts_with("passives: 90.500\n"));
+    assert_eq!(Weights::parse(&text), Ok(w));
+}
+
+#[test]
+fn missing_terms_keep_defaults() {
+    let w = Weights::parse("# tuned\n\nmobility: 7\n").unwrap();
+    let expected = Weights {
+        mobility: 7.0,
+        ..Weights::default()
+    };
+    assert_eq!(w, expected);
+}
+
+#[test]
+fn bad_lines_are_errors() {
+    assert_eq!(Weights::parse("speed: 3"), Err("unknown weight name"));
+    assert_eq!(Weights::parse("mobility 3"), Err("invalid weights line"));
+    assert_eq!(Weights::parse("mobility: fast"), Err("invalid weight"));
+}
+
+fn position(tiles: &str) -> Instance {
+    let mut ctx = Instance::blank();
+    ctx.board = Board::decode(tiles.to_string()).unwrap();
+    ctx
+}
+
+#[test]
+fn start_position_is_level() {
+    let ctx = Instance::start();
+    assert_eq!(terms(&ctx, Side::White), terms(&ctx, Side::Orange));
+    assert_eq!(evaluate(&ctx, &Weights::default()), 0);
+}
+
+#[test]
+fn passives_in_reach_are_threatened() {
+    // the white tiger can take the last orange passive, which adds the last passive bonus
+    let ctx = position("g7/8/8/3t4/3G4/8/8/7O");
+    let white = terms(&ctx, Side::White);
+    assert_eq!((white.passives, white.aggressives), (1, 1));
+    assert_eq!(white.passive_threat, 3);
+    assert_eq!(terms(&ctx, Side::Orange).passive_threat, 2);
+}
+
+#[test]
+fn lined_up_passives_count_squared() {
+    // edge files leave out the home row, so the goat starts on b1
+    let ctx = position("8/g7/h7/s7/8/8/8/7O");
+    let white = terms(&ctx, Side::White);
+    assert_eq!(white.edge_files, 9);
+    assert_eq!(white.blockers, 1);
+}
+
+#[test]
+fn breakdown_adds_up_to_evaluation() {
+    let w = Weights::default();
+    let mut ctx = position("g7/8/8/3t4/3G4/8/8/7O");
+    let white = evaluate(&ctx, &w);
+    let total: i32 = breakdown(&ctx, &w).iter().map(|(_, v)| v).sum();
+    assert_eq!(total, white);
+    ctx.side = Side::Orange;
+    assert_eq!(evaluate(&ctx, &w), -white);
+}
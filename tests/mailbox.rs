@@ -0,0 +1,22 @@
+use gtc::{board::Board, mailbox, rng::Rng};
+
+#[test]
+fn start_position_matches_reference() {
+    assert!(mailbox::diff(&Board::new()).is_empty());
+}
+
+#[test]
+fn random_boards_match_reference() {
+    let mut rng = Rng::new(1);
+    for n in 0..2000 {
+        let board = mailbox::random_board(&mut rng);
+        let mismatches = mailbox::diff(&board);
+        assert!(
+            mismatches.is_empty(),
+            "board #{} {}: {}",
+            n,
+            board.encode(),
+            mismatches[0]
+        );
+    }
+}
@@ -0,0 +1,59 @@
+use gtc::{
+    board::Board,
+    game::{Instance, Outcome},
+    mcts::{mcts, playout, MctsLimits},
+    movegen::Move,
+    piece::Side,
+    rng::Rng,
+};
+
+/// White has called and its tiger on d4 can take the last orange passive
+fn win_in_one() -> Instance {
+    let mut ctx = Instance::blank();
+    ctx.board = Board::decode("g7/8/8/3t4/3G4/8/8/7O".to_string()).unwrap();
+    ctx.call.insert(Side::White, true);
+    ctx
+}
+
+fn iterations(n: u64) -> MctsLimits {
+    MctsLimits {
+        iterations: Some(n),
+        movetime: None,
+    }
+}
+
+#[test]
+fn finds_win_in_one() {
+    let result = mcts(&win_in_one(), &iterations(500), &mut Rng::new(1));
+    let win = Move::decode("t e4").unwrap();
+    assert_eq!(result.best, Some(win));
+    assert_eq!(result.pv.first(), Some(&win));
+    let stats = result.moves.iter().find(|s| s.mv == win).unwrap();
+    assert_eq!(stats.win_rate, 1.0);
+}
+
+#[test]
+fn runs_the_given_iterations_over_every_root_move() {
+    let ctx = Instance::start();
+    let result = mcts(&ctx, &iterations(100), &mut Rng::new(2));
+    assert_eq!(result.iterations, 100);
+    assert_eq!(result.moves.len(), ctx.legal_moves().len());
+    let visits: u32 = result.moves.iter().map(|s| s.visits).sum();
+    assert_eq!(visits, 100);
+    assert!(result.moves.windows(2).all(|w| w[0].visits >= w[1].visits));
+}
+
+#[test]
+fn same_seed_same_search() {
+    let ctx = Instance::start();
+    let a = mcts(&ctx, &iterations(50), &mut Rng::new(3));
+    let b = mcts(&ctx, &iterations(50), &mut Rng::new(3));
+    assert_eq!(a.best, b.best);
+    assert_eq!(a.pv, b.pv);
+}
+
+#[test]
+fn playout_takes_the_win() {
+    let outcome = playout(win_in_one(), &mut Rng::new(4));
+    assert!(matches!(outcome, Some(Outcome::Win(Side::White, _))));
+}
@@ -0,0 +1,43 @@
+#![cfg(feature = "nnue")]
+
+use gtc::{
+    nnue::{feature, FEATURES},
+    piece::{Piece, Side},
+};
+
+#[test]
+fn own_pieces_come_first() {
+    assert_eq!(feature(Side::White, Piece::Goat(Side::White), 0), Some(0));
+    assert_eq!(
+        feature(Side::White, Piece::Goat(Side::Orange), 0),
+        Some(8 * 64)
+    );
+    assert_eq!(
+        feature(Side::White, Piece::MantisShrimp(Side::White), 63),
+        Some(7 * 64 + 63)
+    );
+    assert_eq!(
+        feature(Side::White, Piece::MantisShrimp(Side::Orange), 63),
+        Some(FEATURES - 1)
+    );
+    assert_eq!(feature(Side::White, Piece::None, 0), None);
+}
+
+#[test]
+fn orange_sees_the_board_flipped() {
+    // b row, column 3 is the g row, column 3 from Orange's side
+    assert_eq!(
+        feature(Side::Orange, Piece::Bird(Side::Orange), 10),
+        Some(3 * 64 + 50)
+    );
+    for tile in 0..64 {
+        for k in 0..8 {
+            let white = Piece::from_index(k, Side::White);
+            let orange = Piece::from_index(k, Side::Orange);
+            assert_eq!(
+                feature(Side::White, white, tile),
+                feature(Side::Orange, orange, tile ^ 56)
+            );
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use std::sync::{Arc, Mutex};
+
+use gtc::{
+    board::Board,
+    game::{GameObserver, Instance, Outcome},
+    movegen::Move,
+    piece::{Piece, Side},
+    position::{Normalizable, Position},
+};
+
+/// Writes every event down in the order it arrives
+#[derive(Default)]
+struct Recorder(Mutex<Vec<String>>);
+
+impl Recorder {
+    fn push(&self, event: String) {
+        self.0.lock().unwrap().push(event);
+    }
+
+    fn take(&self) -> Vec<String> {
+        std::mem::take(&mut *self.0.lock().unwrap())
+    }
+}
+
+fn tile(pos: Position) -> String {
+    pos.encode().unwrap()
+}
+
+impl GameObserver for Recorder {
+    fn on_move(&self, _ctx: &Instance, p: Piece, from: Position, to: Position) {
+        self.push(format!("move {} {} {}", p.encode(), tile(from), tile(to)));
+    }
+    fn on_capture(&self, _ctx: &Instance, by: Piece, captured: Piece, at: Position) {
+        self.push(format!(
+            "capture {} {} {}",
+            by.encode(),
+            captured.encode(),
+            tile(at)
+        ));
+    }
+    fn on_call(&self, _ctx: &Instance, side: Side) {
+        self.push(format!("call {}", side));
+    }
+    fn on_miss_call(&self, _ctx: &Instance, side: Side) {
+        self.push(format!("miss-call {}", side));
+    }
+    fn on_game_over(&self, _ctx: &Instance, outcome: Outcome) {
+        self.push(format!("game over {:?}", outcome));
+    }
+}
+
+fn observed(board: &str) -> (Instance, Arc<Recorder>) {
+    let mut ctx = Instance::blank();
+    ctx.board = Board::decode(board.to_string()).unwrap();
+    let recorder = Arc::new(Recorder::default());
+    ctx.observe(recorder.clone());
+    (ctx, recorder)
+}
+
+fn play(ctx: &mut Instance, m: &str) {
+    assert!(ctx.play(Move::decode(m).unwrap()), "{} rejected", m);
+}
+
+#[test]
+fn called_win_reports_capture_then_move_then_result() {
+    let (mut ctx, recorder) = observed("g7/8/8/3t4/3G4/8/8/7O");
+    play(&mut ctx, "c");
+    assert_eq!(recorder.take(), vec!["call White"]);
+    play(&mut ctx, "O g7");
+    play(&mut ctx, "t e4");
+    assert_eq!(
+        recorder.take(),
+        vec![
+            "move O h8 g7",
+            "capture t G e4",
+            "move t d4 e4",
+            "game over Win(White, PassiveElimination)",
+        ]
+    );
+}
+
+#[test]
+fn miss_call_penalty_reports_capture_of_own_piece() {
+    let (mut ctx, recorder) = observed("g7/8/8/3t4/3G4/8/8/7O");
+    play(&mut ctx, "t e4");
+    play(&mut ctx, "O g7");
+    play(&mut ctx, "t a1");
+    assert_eq!(
+        recorder.take(),
+        vec![
+            "capture t G e4",
+            "move t d4 e4",
+            "miss-call White",
+            "move O h8 g7",
+            "capture t g a1",
+            "move t e4 a1",
+        ]
+    );
+}
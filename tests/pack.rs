@@ -0,0 +1,72 @@
+use gtc::{
+    game::Instance,
+    movegen::Move,
+    pack::{PackReader, PackWriter, PackedPosition, PACKED_SIZE},
+    piece::Side,
+};
+
+/// Start position after a few turns, with White having called
+fn played() -> Instance {
+    let mut ctx = Instance::start();
+    for m in ["i b8", "I g8", "c", "G g1"] {
+        assert!(ctx.play(Move::decode(m).unwrap()), "{}", m);
+    }
+    ctx
+}
+
+fn assert_same(a: &Instance, b: &Instance) {
+    assert_eq!(a.board.encode(), b.board.encode());
+    assert_eq!(a.side, b.side);
+    assert_eq!(a.call, b.call);
+    assert_eq!(a.miss_call, b.miss_call);
+    assert_eq!(a.last_move, b.last_move);
+}
+
+#[test]
+fn encode_decode_round_trip() {
+    for ctx in [Instance::start(), played()] {
+        let packed = PackedPosition::encode(&ctx).unwrap();
+        assert_same(&ctx, &packed.decode().unwrap());
+        assert_eq!(
+            PackedPosition::encode(&packed.decode().unwrap()).unwrap(),
+            packed
+        );
+    }
+}
+
+#[test]
+fn flags_byte_layout() {
+    let ctx = played();
+    let packed = PackedPosition::encode(&ctx).unwrap();
+    assert_eq!(ctx.side, Side::White);
+    // white to move and white called
+    assert_eq!(packed.0[16], 0b00011);
+}
+
+#[test]
+fn stream_round_trip() {
+    let positions = [Instance::start(), played()];
+    let mut writer = PackWriter::new(vec![]);
+    for p in positions.iter() {
+        writer.write(p).unwrap();
+    }
+    let bytes = writer.into_inner();
+    assert_eq!(bytes.len(), positions.len() * PACKED_SIZE);
+
+    let read: Vec<Instance> = PackReader::new(bytes.as_slice())
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(read.len(), positions.len());
+    for (a, b) in positions.iter().zip(read.iter()) {
+        assert_same(a, b);
+    }
+}
+
+#[test]
+fn truncated_stream_is_an_error() {
+    let mut writer = PackWriter::new(vec![]);
+    writer.write(&Instance::start()).unwrap();
+    let bytes = writer.into_inner();
+    let mut reader = PackReader::new(&bytes[..PACKED_SIZE - 1]);
+    assert!(reader.next().unwrap().is_err());
+}
@@ -0,0 +1,111 @@
+use gtc::{
+    board::Board,
+    game::Instance,
+    mailbox::{self, Mailbox},
+    movegen::Move,
+    perft,
+    piece::{Piece, Side},
+    rng::Rng,
+};
+
+#[test]
+fn start_position_counts() {
+    let ctx = Instance::start();
+    for (depth, nodes) in [(0, 1), (1, 17), (2, 289), (3, 5270), (4, 96100)] {
+        assert_eq!(perft::perft(&ctx, depth), nodes, "depth {}", depth);
+    }
+}
+
+#[test]
+fn parallel_matches_serial() {
+    let ctx = Instance::start();
+    for depth in 0..4 {
+        assert_eq!(
+            perft::perft_parallel(&ctx, depth),
+            perft::perft(&ctx, depth)
+        );
+    }
+}
+
+#[test]
+fn divide_sums_to_perft() {
+    let ctx = Instance::start();
+    let total: u64 = perft::divide(&ctx, 3).iter().map(|(_, n)| n).sum();
+    assert_eq!(total, perft::perft(&ctx, 3));
+}
+
+#[test]
+fn legal_moves_match_reference_and_are_played() {
+    let mut rng = Rng::new(2);
+    for n in 0..1000 {
+        let mut ctx = Instance::blank();
+        ctx.board = mailbox::random_board(&mut rng);
+        ctx.side = if rng.chance(0.5) {
+            Side::White
+        } else {
+            Side::Orange
+        };
+        let reference = Mailbox::from_board(&ctx.board);
+        for m in ctx.legal_moves() {
+            if let Move::Step(p, to) = m {
+                let mut mask = reference.move_mask(p).unwrap();
+                assert!(
+                    mask.position(to),
+                    "board #{} {}: {}",
+                    n,
+                    ctx.board.encode(),
+                    m
+                );
+            }
+            assert!(
+                ctx.clone().play(m),
+                "board #{} {}: {} rejected",
+                n,
+                ctx.board.encode(),
+                m
+            );
+        }
+    }
+}
+
+/// Orange to move after White's tiger took the last orange passive without calling
+fn pending_miss_call() -> Instance {
+    let mut ctx = Instance::blank();
+    ctx.board = Board::decode("g7/8/8/3t4/3G4/8/8/7O".to_string()).unwrap();
+    assert!(ctx.play(Move::decode("t e4").unwrap()));
+    assert!(ctx.miss_call[&Side::White]);
+    ctx
+}
+
+#[test]
+fn miss_call_penalty_counts() {
+    let ctx = pending_miss_call();
+    // two orange turns, each answered by one of the eight home tiles
+    for (depth, nodes) in [(1, 2), (2, 16), (3, 48)] {
+        assert_eq!(perft::perft(&ctx, depth), nodes, "depth {}", depth);
+    }
+    for (m, _) in perft::divide(&ctx, 1) {
+        let mut child = ctx.clone();
+        child.play(m);
+        let penalties = child.legal_moves();
+        assert_eq!(penalties.len(), 8, "after {}", m);
+        for p in penalties {
+            assert!(child.clone().play(p), "after {}: {} rejected", m, p);
+        }
+    }
+}
+
+#[test]
+fn miss_call_penalty_moves_the_piece() {
+    // the white goat owing the penalty already stands on a home tile
+    let mut ctx = Instance::blank();
+    ctx.board = Board::decode("g7/8/8/8/8/8/8/7O".to_string()).unwrap();
+    ctx.miss_call.insert(Side::White, true);
+    ctx.last_move.insert(Side::White, Piece::Goat(Side::White));
+    let stay = Move::decode("g a1").unwrap();
+    let penalties = ctx.legal_moves();
+    assert_eq!(penalties.len(), 7);
+    assert!(!penalties.contains(&stay));
+    assert!(!ctx.clone().play(stay));
+    assert!(ctx.play(Move::decode("g a5").unwrap()));
+}
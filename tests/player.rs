@@ -0,0 +1,174 @@
+use std::collections::HashSet;
+
+use gtc::{
+    board::Board,
+    game::Instance,
+    movegen::Move,
+    piece::Side,
+    player::{
+        self, AutoCall, CallPolicy, GreedyPlayer, Player, PlayerConfig, RandomPlayer, SkillPlayer,
+        MAX_SKILL,
+    },
+    rng::Rng,
+    search::Limits,
+};
+
+fn spec(s: &str) -> Result<Box<dyn Player>, &'static str> {
+    player::from_spec(s, 1, &PlayerConfig::default())
+}
+
+#[test]
+fn specs_name_their_players() {
+    for s in [
+        "random",
+        "greedy",
+        "lookahead:3",
+        "mcts:50",
+        "lookahead:2,call=0.5,book",
+    ] {
+        assert_eq!(spec(s).unwrap().name(), s);
+    }
+    assert_eq!(spec("lookahead").unwrap().name(), "lookahead:4");
+}
+
+#[test]
+fn bad_specs_are_rejected() {
+    assert!(spec("minimax").is_err());
+    assert!(spec("lookahead:deep").is_err());
+    assert_eq!(spec("skill:21").err(), Some("skill goes from 0 to 20"));
+    assert_eq!(spec("random,ponder").err(), Some("unknown player option"));
+    assert!(spec("random,call=2").is_err());
+}
+
+#[test]
+fn random_player_plays_legal_turns() {
+    let mut p = RandomPlayer::new(Rng::new(3));
+    let mut ctx = Instance::start();
+    for _ in 0..40 {
+        if ctx.outcome.is_some() {
+            break;
+        }
+        let m = p.choose(&ctx).unwrap();
+        assert!(ctx.legal_moves().contains(&m));
+        assert!(ctx.play(m));
+    }
+}
+
+#[test]
+fn greedy_player_takes_passives_first() {
+    // the white tiger on d4 can take the orange goat on e4 or the orange tiger on e3
+    let mut ctx = Instance::blank();
+    ctx.board = Board::decode("g7/8/8/3t4/2TG4/8/8/7O".to_string()).unwrap();
+    assert!(ctx.legal_moves().contains(&Move::decode("t e3").unwrap()));
+    let mut p = GreedyPlayer::new(Rng::new(4));
+    assert_eq!(p.choose(&ctx), Some(Move::decode("t e4").unwrap()));
+}
+
+#[test]
+fn games_between_bots_replay() {
+    let mut white = spec("random").unwrap();
+    let mut orange = spec("greedy").unwrap();
+    let result = player::play_game(&Instance::start(), white.as_mut(), orange.as_mut(), 60);
+    let positions = result.record.replay();
+    assert_eq!(positions.len(), result.record.moves.len() + 1);
+    assert_eq!(positions.last().unwrap().outcome, result.outcome);
+    if result.outcome.is_none() {
+        assert_eq!(result.record.moves.len(), 60);
+    }
+    assert_eq!(positions[1].side, Side::Orange);
+}
+
+/// White tiger on d4 next to the last orange passive, nobody has called
+fn passive_in_reach() -> Instance {
+    let mut ctx = Instance::blank();
+    ctx.board = Board::decode("g7/8/8/3t4/3G4/8/8/7O".to_string()).unwrap();
+    ctx
+}
+
+#[test]
+fn call_policies_parse_and_print() {
+    for (s, policy) in [
+        ("always", CallPolicy::Always),
+        ("never", CallPolicy::Never),
+        ("0.25", CallPolicy::Chance(0.25)),
+    ] {
+        assert_eq!(s.parse::<CallPolicy>(), Ok(policy));
+        assert_eq!(policy.to_string(), s);
+    }
+    assert!("1.5".parse::<CallPolicy>().is_err());
+    assert!("sometimes".parse::<CallPolicy>().is_err());
+}
+
+#[test]
+fn can_call_until_called() {
+    let mut ctx = passive_in_reach();
+    let win = Move::decode("t e4").unwrap();
+    assert!(ctx.can_call());
+    assert_eq!(ctx.winning_steps(), vec![win]);
+    ctx.call.insert(Side::White, true);
+    assert!(!ctx.can_call());
+    assert_eq!(ctx.winning_steps(), vec![win]);
+    ctx.side = Side::Orange;
+    assert!(!ctx.can_call());
+}
+
+#[test]
+fn auto_call_follows_its_policy() {
+    let ctx = passive_in_reach();
+    let win = Move::decode("t e4").unwrap();
+    for (policy, expected) in [
+        (CallPolicy::Always, Move::Call),
+        (CallPolicy::Chance(1.0), Move::Call),
+        (CallPolicy::Never, win),
+        (CallPolicy::Chance(0.0), win),
+    ] {
+        let inner = Box::new(RandomPlayer::new(Rng::new(5)));
+        let mut p = AutoCall::new(inner, policy, Rng::new(6));
+        assert_eq!(p.choose(&ctx), Some(expected), "{}", policy);
+    }
+}
+
+#[test]
+fn uncalled_winning_step_is_a_miss_call() {
+    let mut ctx = passive_in_reach();
+    let mut p = spec("random,call=never").unwrap();
+    let m = p.choose(&ctx).unwrap();
+    assert!(ctx.play(m));
+    assert!(ctx.miss_call[&Side::White]);
+    assert!(ctx.outcome.is_none());
+}
+
+#[test]
+fn skill_sets_depth_margin_and_calls() {
+    let at = |skill| SkillPlayer::new(skill, Limits::default(), Rng::new(7));
+    assert_eq!((at(0).depth(), at(0).margin()), (1, 300));
+    assert_eq!((at(20).depth(), at(20).margin()), (5, 0));
+    assert_eq!(at(0).call_policy(), CallPolicy::Chance(0.5));
+    assert_eq!(at(20).call_policy(), CallPolicy::Chance(1.0));
+    assert_eq!(at(99).skill, MAX_SKILL);
+    assert_eq!(spec("skill").unwrap().name(), "skill:10,call=0.75");
+}
+
+#[test]
+fn full_skill_takes_the_win() {
+    let mut ctx = passive_in_reach();
+    ctx.call.insert(Side::White, true);
+    for seed in 0..5 {
+        let mut p = SkillPlayer::new(MAX_SKILL, Limits::default(), Rng::new(seed));
+        assert_eq!(p.choose(&ctx), Some(Move::decode("t e4").unwrap()));
+    }
+}
+
+#[test]
+fn low_skill_still_plays_legal_turns() {
+    let ctx = Instance::start();
+    let mut p = SkillPlayer::new(0, Limits::default(), Rng::new(8));
+    let mut picked = HashSet::new();
+    for _ in 0..20 {
+        let m = p.choose(&ctx).unwrap();
+        assert!(ctx.legal_moves().contains(&m));
+        picked.insert(m);
+    }
+    // a wide margin leaves more than the best move to pick from
+    assert!(picked.len() > 1);
+}
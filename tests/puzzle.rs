@@ -0,0 +1,88 @@
+use gtc::{
+    board::Board,
+    game::Instance,
+    movegen::Move,
+    piece::Side,
+    puzzle::{self, Puzzle, Stats, Theme},
+};
+
+const LINE: &str =
+    "2tolm2/g7/h7/s7/i7/8/8/GHTOLMSI White -; c, G g1, i f1, G f2, s e1; miss-call trap";
+
+#[test]
+fn parse_display_round_trip() {
+    let p: Puzzle = LINE.parse().unwrap();
+    assert_eq!(p.position.side, Side::White);
+    assert!(!p.position.call[&Side::White] && !p.position.call[&Side::Orange]);
+    assert_eq!(p.solution.len(), 5);
+    assert_eq!(p.solution[0], Move::Call);
+    assert_eq!(p.theme, Theme::MissCallTrap);
+    assert_eq!(p.to_string(), LINE);
+}
+
+#[test]
+fn win_that_needs_a_call_is_a_trap() {
+    let mut ctx = Instance::blank();
+    ctx.board = Board::decode("8/h1i3M1/8/8/8/2l1o3/2S5/2s".to_string()).unwrap();
+    let p = puzzle::find(&ctx, 3).unwrap();
+    assert_eq!(p.solution[0], Move::Call);
+    assert_eq!(p.theme, Theme::MissCallTrap);
+    assert!(p.is_win());
+}
+
+#[test]
+fn calls_are_read_per_side() {
+    let line = LINE.replace("White -", "Orange WO");
+    let p: Puzzle = line.parse().unwrap();
+    assert_eq!(p.position.side, Side::Orange);
+    assert!(p.position.call[&Side::White] && p.position.call[&Side::Orange]);
+    assert_eq!(p.to_string(), line);
+}
+
+#[test]
+fn themes_round_trip() {
+    for t in [
+        Theme::EdgeAlignment,
+        Theme::PassiveElimination,
+        Theme::MissCallTrap,
+    ] {
+        assert_eq!(t.to_string().parse::<Theme>(), Ok(t));
+    }
+}
+
+#[test]
+fn bad_lines_are_errors() {
+    for line in [
+        "",
+        "2tolm2/g7/h7/s7/i7/8/8/GHTOLMSI White -; c",
+        "2tolm2/g7/h7/s7/i7/8/8/GHTOLMSI White; c; edge alignment",
+        "2tolm2/g7/h7/s7/i7/8/8/GHTOLMSI White X; c; edge alignment",
+        "2tolm2/g7/h7/s7/i7/8/8/GHTOLMSI White -; ; edge alignment",
+        "2tolm2/g7/h7/s7/i7/8/8/GHTOLMSI White -; c; fork",
+    ] {
+        assert!(line.parse::<Puzzle>().is_err(), "{}", line);
+    }
+}
+
+#[test]
+fn stats_parse_and_display() {
+    let mut stats = Stats::parse("3 4\n").unwrap();
+    assert_eq!(
+        stats,
+        Stats {
+            solved: 3,
+            attempted: 4
+        }
+    );
+    assert_eq!(stats.to_string(), "3/4 solved (75%)");
+    stats.add(false);
+    assert_eq!(stats.to_string(), "3/5 solved (60%)");
+    assert_eq!(Stats::default().to_string(), "0/0 solved (0%)");
+}
+
+#[test]
+fn bad_stats_are_errors() {
+    for raw in ["", "3", "3 4 5", "x 4", "5 4"] {
+        assert!(Stats::parse(raw).is_err(), "{}", raw);
+    }
+}
@@ -0,0 +1,58 @@
+use gtc::{
+    board::Board,
+    game::{Instance, Outcome, WinReason},
+    movegen::Move,
+    piece::Side,
+    record::GameRecord,
+};
+
+#[test]
+fn parse_encode_round_trip() {
+    let raw = format!("{} White\ni b8,I g8,c,G g1,", Board::new().encode());
+    let record = GameRecord::parse(&raw).unwrap();
+    assert_eq!(record.moves.len(), 4);
+    assert_eq!(record.moves[2], Move::Call);
+    assert_eq!(record.encode(), raw);
+}
+
+#[test]
+fn replay_skips_rejected_moves() {
+    // the second "i b8" is played out of turn
+    let raw = format!("{} White\ni b8,i b8,I g8,c,G g1,", Board::new().encode());
+    let positions = GameRecord::parse(&raw).unwrap().replay();
+    assert_eq!(positions.len(), 5);
+
+    let mut ctx = Instance::start();
+    for m in ["i b8", "I g8", "c", "G g1"] {
+        assert!(ctx.play(Move::decode(m).unwrap()));
+    }
+    let last = positions.last().unwrap();
+    assert_eq!(last.board.encode(), ctx.board.encode());
+    assert_eq!(last.side, ctx.side);
+    assert!(last.outcome.is_none());
+}
+
+#[test]
+fn steps_pair_accepted_moves_with_positions() {
+    let raw = format!("{} White\ni b8,i b8,I g8,", Board::new().encode());
+    let steps = GameRecord::parse(&raw).unwrap().steps();
+    let moves: Vec<Move> = steps.iter().map(|(_, m)| *m).collect();
+    assert_eq!(
+        moves,
+        vec![Move::decode("i b8").unwrap(), Move::decode("I g8").unwrap()]
+    );
+    assert_eq!(steps[1].0.side, Side::Orange);
+    assert_eq!(steps[0].0.board.encode(), Board::new().encode());
+}
+
+#[test]
+fn uncalled_win_is_taken_from_final_position() {
+    // the white tiger on d4 takes the only orange passive, without calling first
+    let record = GameRecord::parse("g7/8/8/3t4/3G4/8/8/8 White\nt e4,").unwrap();
+    let positions = record.replay();
+    assert_eq!(positions.len(), 2);
+    assert_eq!(
+        positions[1].outcome,
+        Some(Outcome::Win(Side::White, WinReason::PassiveElimination))
+    );
+}
@@ -0,0 +1,101 @@
+use std::time::Duration;
+
+use gtc::{
+    board::Board,
+    game::Instance,
+    movegen::Move,
+    piece::{Piece, Side},
+    search::{is_win_score, rank_moves, search, Limits, WIN},
+};
+
+/// White tiger on d4 next to the last orange passive
+fn passive_in_reach(called: bool) -> Instance {
+    let mut ctx = Instance::blank();
+    ctx.board = Board::decode("g7/8/8/3t4/3G4/8/8/7O".to_string()).unwrap();
+    ctx.call.insert(Side::White, called);
+    ctx
+}
+
+fn depth(d: u32) -> Limits {
+    Limits {
+        depth: Some(d),
+        ..Limits::default()
+    }
+}
+
+#[test]
+fn finds_win_in_one() {
+    let result = search(&passive_in_reach(true), &depth(3), |_| {});
+    assert_eq!(result.best, Some(Move::decode("t e4").unwrap()));
+    assert_eq!(result.score, WIN - 1);
+    assert_eq!(result.pv, vec![Move::decode("t e4").unwrap()]);
+}
+
+#[test]
+fn defender_steps_out_of_reach() {
+    let mut ctx = passive_in_reach(true);
+    ctx.side = Side::Orange;
+    let result = search(&ctx, &depth(2), |_| {});
+    assert!(matches!(
+        result.best,
+        Some(Move::Step(Piece::Goat(Side::Orange), _))
+    ));
+    assert!(!is_win_score(result.score));
+}
+
+#[test]
+fn reports_each_depth_until_the_win() {
+    let mut depths = vec![];
+    let result = search(&passive_in_reach(true), &depth(5), |info| {
+        depths.push(info.depth)
+    });
+    assert!(is_win_score(result.score));
+    assert_eq!(depths, vec![1]);
+}
+
+#[test]
+fn move_time_still_gives_a_move() {
+    let limits = Limits {
+        movetime: Some(Duration::from_millis(20)),
+        ..Limits::default()
+    };
+    let ctx = Instance::start();
+    let best = search(&ctx, &limits, |_| {}).best.unwrap();
+    assert!(ctx.legal_moves().contains(&best));
+}
+
+#[test]
+fn ranks_every_move_best_first() {
+    let ctx = passive_in_reach(true);
+    let ranked = rank_moves(&ctx, &depth(2), None);
+    assert_eq!(ranked.len(), ctx.legal_moves().len());
+    assert_eq!(ranked[0].mv, Move::decode("t e4").unwrap());
+    assert_eq!(ranked[0].score, WIN - 1);
+    assert!(ranked.windows(2).all(|w| w[0].score >= w[1].score));
+    assert!(ranked.iter().all(|r| r.pv.first() == Some(&r.mv)));
+}
+
+#[test]
+fn ranked_best_matches_search() {
+    let ctx = Instance::start();
+    let ranked = rank_moves(&ctx, &depth(3), None);
+    let result = search(&ctx, &depth(3), |_| {});
+    assert_eq!(ranked[0].score, result.score);
+}
+
+#[test]
+fn margin_leaves_out_worse_moves() {
+    let ctx = Instance::start();
+    let all = rank_moves(&ctx, &depth(2), None);
+    let near = rank_moves(&ctx, &depth(2), Some(10));
+    let best = all[0].score;
+    let expected: Vec<(Move, i32)> = all
+        .iter()
+        .filter(|r| r.score >= best - 10)
+        .map(|r| (r.mv, r.score))
+        .collect();
+    let got: Vec<(Move, i32)> = near.iter().map(|r| (r.mv, r.score)).collect();
+    assert_eq!(got.len(), expected.len());
+    assert!(got.iter().all(|m| expected.contains(m)));
+    assert!(near.len() < all.len());
+}
@@ -0,0 +1,33 @@
+use gtc::{
+    rng::Rng,
+    selfplay::{play_game, SelfPlayConfig},
+};
+
+fn config() -> SelfPlayConfig {
+    SelfPlayConfig {
+        depth: 1,
+        max_plies: 40,
+        ..SelfPlayConfig::default()
+    }
+}
+
+#[test]
+fn same_seed_same_game() {
+    let a = play_game(&config(), &mut Rng::new(7));
+    let b = play_game(&config(), &mut Rng::new(7));
+    assert_eq!(a.record.encode(), b.record.encode());
+    assert_eq!(a.positions, b.positions);
+}
+
+#[test]
+fn forced_moves_are_not_labelled() {
+    for seed in 0..5 {
+        let game = play_game(&config(), &mut Rng::new(seed));
+        assert!(game.positions.len() <= game.record.moves.len());
+        for r in game.positions.iter() {
+            let ctx = r.position.decode().unwrap();
+            assert!(ctx.legal_moves().len() > 1, "{}", ctx.board.encode());
+            assert_eq!(r.result, game.outcome);
+        }
+    }
+}
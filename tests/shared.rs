@@ -0,0 +1,96 @@
+use std::{thread, time::Duration};
+
+use gtc::{
+    clock::Clocks,
+    game::{Instance, Outcome, WinReason},
+    movegen::Move,
+    piece::Side,
+    record::GameRecord,
+    shared::GameHandle,
+};
+
+fn submit(game: &GameHandle, side: Side, m: &str) -> Result<(), &'static str> {
+    match Move::decode(m).unwrap() {
+        Move::Step(p, pos) => game.submit_move(side, p, pos),
+        Move::Call => game.submit_call(side),
+    }
+}
+
+#[test]
+fn accepted_turns_are_logged() {
+    let game = GameHandle::new(Instance::start());
+    submit(&game, Side::White, "i b8").unwrap();
+    submit(&game, Side::Orange, "I g8").unwrap();
+    submit(&game, Side::White, "c").unwrap();
+    submit(&game, Side::Orange, "G g1").unwrap();
+    assert_eq!(game.states(), "i b8,I g8,c,G g1,");
+    assert_eq!(game.side(), Side::White);
+}
+
+#[test]
+fn rejected_turns_change_nothing() {
+    let game = GameHandle::new(Instance::start());
+    assert_eq!(submit(&game, Side::Orange, "I g8"), Err("not your turn"));
+    assert_eq!(submit(&game, Side::Orange, "c"), Err("not your turn"));
+    assert_eq!(submit(&game, Side::White, "i e8"), Err("invalid move"));
+    assert_eq!(game.states(), "");
+    assert_eq!(game.side(), Side::White);
+    assert_eq!(
+        game.snapshot().board.encode(),
+        Instance::start().board.encode()
+    );
+}
+
+#[test]
+fn flag_fall_ends_the_game() {
+    let mut ctx = Instance::start();
+    let mut clocks = Clocks::new(Duration::ZERO, Duration::ZERO);
+    clocks.start();
+    ctx.clocks = Some(clocks);
+    let game = GameHandle::new(ctx);
+
+    assert_eq!(submit(&game, Side::White, "i b8"), Err("out of time"));
+    assert_eq!(
+        game.outcome(),
+        Some(Outcome::Win(Side::Orange, WinReason::Timeout))
+    );
+    assert_eq!(submit(&game, Side::White, "c"), Err("game over"));
+    assert_eq!(game.states(), "");
+}
+
+#[test]
+fn readers_always_see_a_matching_log() {
+    let mut start = Instance::start();
+    start.log_board();
+    let game = GameHandle::new(start);
+
+    let readers: Vec<_> = (0..4)
+        .map(|_| {
+            let game = game.clone();
+            thread::spawn(move || {
+                for _ in 0..200 {
+                    let snap = game.snapshot();
+                    let record = GameRecord::parse(&snap.states).unwrap();
+                    let last = record.replay().pop().unwrap();
+                    assert_eq!(last.board.encode(), snap.board.encode());
+                    assert_eq!(last.side, snap.side);
+                }
+            })
+        })
+        .collect();
+
+    for _ in 0..40 {
+        let ctx = game.snapshot();
+        if ctx.outcome.is_some() {
+            break;
+        }
+        let m = ctx.legal_moves().into_iter().find(|m| *m != Move::Call);
+        match m {
+            Some(Move::Step(p, pos)) => game.submit_move(ctx.side, p, pos).unwrap(),
+            _ => break,
+        }
+    }
+    for r in readers {
+        r.join().unwrap();
+    }
+}
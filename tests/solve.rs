@@ -0,0 +1,63 @@
+use gtc::{
+    board::Board,
+    game::{Instance, Outcome},
+    movegen::Move,
+    piece::Side,
+    solve::{solve, Proof},
+};
+
+/// White tiger on d4 next to the last orange passive
+fn passive_in_reach(called: bool) -> Instance {
+    let mut ctx = Instance::blank();
+    ctx.board = Board::decode("g7/8/8/3t4/3G4/8/8/7O".to_string()).unwrap();
+    ctx.call.insert(Side::White, called);
+    ctx
+}
+
+#[test]
+fn proves_win_in_one() {
+    let result = solve(&passive_in_reach(true), 3, 10_000);
+    assert_eq!(
+        result.proof,
+        Proof::Win(vec![Move::decode("t e4").unwrap()])
+    );
+}
+
+#[test]
+fn proves_win_that_needs_a_call_first() {
+    // whatever orange does after the call, white wins with its next step
+    let mut ctx = Instance::blank();
+    ctx.board = Board::decode("8/h1i3M1/8/8/8/2l1o3/2S5/2s".to_string()).unwrap();
+    let line = match solve(&ctx, 3, 100_000).proof {
+        Proof::Win(line) => line,
+        p => panic!("{:?}", p),
+    };
+    assert_eq!(line.len(), 3);
+    assert_eq!(line[0], Move::Call);
+    let mut played = ctx.clone();
+    for m in line {
+        assert!(played.play(m));
+    }
+    assert!(matches!(played.outcome, Some(Outcome::Win(Side::White, _))));
+    // two turns aren't enough
+    assert_eq!(solve(&ctx, 2, 100_000).proof, Proof::NoWin);
+}
+
+#[test]
+fn escape_disproves_the_win() {
+    // calling first gives the orange goat a turn to step away
+    let result = solve(&passive_in_reach(false), 3, 10_000);
+    assert_eq!(result.proof, Proof::NoWin);
+}
+
+#[test]
+fn start_position_has_no_quick_win() {
+    let result = solve(&Instance::start(), 3, 100_000);
+    assert_eq!(result.proof, Proof::NoWin);
+}
+
+#[test]
+fn node_limit_leaves_it_unknown() {
+    let result = solve(&Instance::start(), 7, 50);
+    assert_eq!(result.proof, Proof::Unknown);
+}
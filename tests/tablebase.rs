@@ -0,0 +1,106 @@
+use std::collections::HashMap;
+
+use gtc::{
+    game::{Instance, Outcome},
+    mailbox,
+    movegen::Move,
+    piece::{Piece, Side},
+    rng::Rng,
+    tablebase::{self, Material, Table, TbResult},
+};
+
+fn random_position(rng: &mut Rng) -> Instance {
+    let mut ctx = Instance::blank();
+    ctx.board = mailbox::random_board(rng);
+    ctx.side = if rng.chance(0.5) {
+        Side::White
+    } else {
+        Side::Orange
+    };
+    ctx.call.insert(Side::White, true);
+    ctx.call.insert(Side::Orange, true);
+    ctx
+}
+
+#[test]
+fn unmake_then_play_restores_position() {
+    let mut rng = Rng::new(3);
+    for n in 0..500 {
+        let ctx = random_position(&mut rng);
+        for u in ctx.unmoves() {
+            let mut before = ctx.unmake(u);
+            assert!(
+                before.play(Move::Step(u.piece, u.to)),
+                "board #{} {}: unmove {:?} can't be played forward",
+                n,
+                ctx.board.encode(),
+                u
+            );
+            assert_eq!(before.board.encode(), ctx.board.encode(), "board #{}", n);
+            assert_eq!(before.side, ctx.side, "board #{}", n);
+        }
+    }
+}
+
+#[test]
+fn unmoves_cover_every_quiet_step() {
+    let mut rng = Rng::new(4);
+    for n in 0..500 {
+        let ctx = random_position(&mut rng);
+        for m in ctx.legal_moves() {
+            let (p, to) = match m {
+                Move::Step(p, to) if ctx.piece_at(to) == Piece::None => (p, to),
+                _ => continue,
+            };
+            let mut board = ctx.board;
+            let from = board.pos_from_piece(p).unwrap();
+            let mut child = ctx.clone();
+            assert!(child.play(m));
+            assert!(
+                child
+                    .unmoves()
+                    .iter()
+                    .any(|u| u.piece == p && u.from == from && u.to == to),
+                "board #{} {}: {} has no unmove",
+                n,
+                ctx.board.encode(),
+                m
+            );
+        }
+    }
+}
+
+#[test]
+fn table_save_load_probe_round_trip() {
+    let dir = std::env::temp_dir().join(format!("gtc-tablebase-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let material: Material = "gvi".parse().unwrap();
+    let mut tables = HashMap::new();
+    tablebase::generate_all(&material, &dir, &mut tables, &mut |_| {}).unwrap();
+
+    let table = &tables[&material];
+    let loaded = Table::load(&tablebase::table_path(&dir, &material)).unwrap();
+    assert_eq!(loaded.material, table.material);
+    assert_eq!(loaded.values, table.values);
+
+    let mut wins = 0;
+    for i in 0..material.size() {
+        let ctx = match material.position(i) {
+            Some(ctx) => ctx,
+            None => continue,
+        };
+        let value = table.probe(&ctx).unwrap();
+        assert_eq!(loaded.probe(&ctx), Some(value));
+        assert_eq!(tablebase::probe_dir(&dir, &ctx), Ok(value));
+        if value == TbResult::Win(1) {
+            wins += 1;
+            let wins_now = ctx.legal_moves().into_iter().any(|m| {
+                let mut child = ctx.clone();
+                child.play(m) && matches!(child.outcome, Some(Outcome::Win(s, _)) if s == ctx.side)
+            });
+            assert!(wins_now, "position {} {}", ctx.board.encode(), ctx.side);
+        }
+    }
+    assert!(wins > 0);
+    std::fs::remove_dir_all(&dir).unwrap();
+}